@@ -0,0 +1,113 @@
+//! Command line arguments that every program playing a playlist takes:
+//! the playlist itself, `--fps`, `--rows` and `--cols`.
+//!
+//! ```no_run
+//! # extern crate clap;
+//! # extern crate lightbox;
+//! use clap::App;
+//! use lightbox::cli::{self, Options};
+//!
+//! # fn main() -> Result<(), String> {
+//! let help = cli::playlist_help();
+//! let matches = App::new("player")
+//!     .arg(cli::playlist_arg(&help))
+//!     .args(&cli::display_args())
+//!     .get_matches();
+//! let options = Options::from_matches(&matches)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::str::FromStr;
+
+use clap::{Arg, ArgMatches};
+use rpi_ws281x_display::animations;
+
+use playlist::PlaylistEntry;
+
+/// The help for `playlist_arg`, which lists the animations.
+pub fn playlist_help() -> String {
+    format!("Animations to play as NAME or NAME:SECONDS, one of: {}", animations::NAMES.join(", "))
+}
+
+/// The playlist as any number of `PlaylistEntry`s, see `playlist_help`.
+pub fn playlist_arg(help: &str) -> Arg<'_, '_> {
+    Arg::with_name("playlist").multiple(true).help(help)
+}
+
+/// `--fps`, `--rows` and `--cols`, for a 7 by 7 display at 30 frames per
+/// second by default.
+pub fn display_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("fps").long("fps").takes_value(true).default_value("30"),
+        Arg::with_name("rows").long("rows").takes_value(true).default_value("7"),
+        Arg::with_name("cols").long("cols").takes_value(true).default_value("7"),
+    ]
+}
+
+/// The value of the argument `name`, which has to be there.
+pub fn parse<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<T, String> {
+    let value = matches.value_of(name).unwrap();
+    value.parse().map_err(|_| format!("invalid value {:?} for --{}", value, name))
+}
+
+/// The playlist and display arguments, checked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// Every animation when none are given.
+    pub playlist: Vec<PlaylistEntry>,
+    pub fps: u32,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl Options {
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let playlist = match matches.values_of("playlist") {
+            Some(values) => values
+                .map(|s| s.parse::<PlaylistEntry>().map_err(|e| e.to_string()))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => animations::NAMES.iter().map(|&name| PlaylistEntry::new(name, None)).collect(),
+        };
+        let fps: u32 = parse(matches, "fps")?;
+        if fps == 0 {
+            return Err("--fps must be greater than 0".to_string());
+        }
+        let (cols, rows): (usize, usize) = (parse(matches, "cols")?, parse(matches, "rows")?);
+        if cols == 0 || rows == 0 {
+            return Err("--cols and --rows must be greater than 0".to_string());
+        }
+        Ok(Options { playlist, fps, cols, rows })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::App;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        let matches = App::new("test")
+            .arg(playlist_arg("help"))
+            .args(&display_args())
+            .get_matches_from(Some("test").iter().chain(args));
+        Options::from_matches(&matches)
+    }
+
+    #[test]
+    fn test_options() {
+        let defaults = options(&[]).unwrap();
+        assert_eq!(defaults.playlist.len(), animations::NAMES.len());
+        assert_eq!((defaults.fps, defaults.cols, defaults.rows), (30, 7, 7));
+
+        assert_eq!(options(&["--fps", "60", "--cols", "16", "circle:5", "fireflies"]), Ok(Options {
+            playlist: vec![PlaylistEntry::new("circle", Some(5.0)), PlaylistEntry::new("fireflies", None)],
+            fps: 60,
+            cols: 16,
+            rows: 7,
+        }));
+        assert!(options(&["nope"]).is_err());
+        assert_eq!(options(&["--fps", "fast"]), Err("invalid value \"fast\" for --fps".to_string()));
+        assert!(options(&["--fps", "0"]).is_err());
+        assert!(options(&["--rows", "0"]).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The source of time for a `LightBox`.
+///
+/// Animations only ever see the time reported by the clock, so swapping
+/// the clock out makes it possible to render faster or slower than real
+/// time.
+pub trait Clock {
+    /// The number of seconds since some fixed point in time.
+    fn now(&self) -> f64;
+    /// Waits until `seconds` have passed according to this clock.
+    fn sleep(&mut self, seconds: f64);
+}
+
+/// Wall-clock time.
+pub struct RealClock {
+    start: Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        RealClock { start: Instant::now() }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        RealClock::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    fn sleep(&mut self, seconds: f64) {
+        if seconds > 0.0 {
+            thread::sleep(Duration::from_secs_f64(seconds));
+        }
+    }
+}
+
+/// A clock that only moves when it is told to. Sleeping returns
+/// immediately after moving the clock forward, so a `LightBox` driven by
+/// one advances exactly one frame per `update` no matter how long the
+/// frame took to draw.
+#[derive(Default)]
+pub struct VirtualClock {
+    now: f64,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        VirtualClock { now: 0.0 }
+    }
+
+    /// Moves the clock forward without going through `sleep`.
+    pub fn advance(&mut self, seconds: f64) {
+        self.now += seconds.max(0.0);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> f64 {
+        self.now
+    }
+
+    fn sleep(&mut self, seconds: f64) {
+        self.advance(seconds);
+    }
+}
+
+/// Runs another clock faster or slower, e.g. a scale of 0.25 plays
+/// animations in slow motion at a quarter of their speed and 4.0 fast
+/// forwards through them.
+///
+/// The frame rate of a `LightBox` is measured in scaled time, so at 0.25x
+/// every frame that would have been shown is still shown, each one held
+/// four times as long.
+pub struct ScaledClock<C: Clock> {
+    inner: C,
+    scale: f64,
+    origin: f64,
+    offset: f64,
+}
+
+impl<C: Clock> ScaledClock<C> {
+    pub fn new(inner: C, scale: f64) -> Self {
+        let origin = inner.now();
+        ScaledClock { inner, scale, origin, offset: 0.0 }
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Changes the speed from now on without making the time jump.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.offset = self.now();
+        self.origin = self.inner.now();
+        self.scale = scale;
+    }
+}
+
+impl<C: Clock> Clock for ScaledClock<C> {
+    fn now(&self) -> f64 {
+        self.offset + (self.inner.now() - self.origin) * self.scale
+    }
+
+    fn sleep(&mut self, seconds: f64) {
+        if self.scale > 0.0 {
+            self.inner.sleep(seconds / self.scale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled_clock() {
+        let mut clock = ScaledClock::new(VirtualClock::new(), 0.25);
+        clock.sleep(1.0);
+        assert_eq!(clock.now(), 1.0);
+        assert_eq!(clock.inner.now(), 4.0);
+
+        clock.set_scale(2.0);
+        clock.sleep(1.0);
+        assert_eq!(clock.now(), 2.0);
+        assert_eq!(clock.inner.now(), 4.5);
+    }
+}
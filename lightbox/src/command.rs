@@ -0,0 +1,45 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Something to do to a running `LightBox`, usually because of user input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Next,
+    Previous,
+    /// Pauses when playing and resumes when paused.
+    TogglePause,
+    Pause,
+    Resume,
+    /// Pauses and moves the animation on by a single frame.
+    Step,
+    /// Jumps to the animation at this position in the playlist.
+    Select(usize),
+    /// Changes the brightness by this much, staying within 0 to 255.
+    AdjustBrightness(i16),
+    SetBrightness(u8),
+    /// Stops `LightBox::run`.
+    Quit,
+}
+
+/// A queue of commands for `LightBox::run`, see `RunOptions`. Clones share
+/// the same queue, so whatever produces commands, such as a window or
+/// another thread, can hold on to one while the light box runs.
+#[derive(Clone, Debug, Default)]
+pub struct Commands {
+    queue: Arc<Mutex<VecDeque<Command>>>,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, command: Command) {
+        self.queue.lock().unwrap().push_back(command);
+    }
+
+    /// Takes the oldest command that hasn't been handled yet.
+    pub fn pop(&self) -> Option<Command> {
+        self.queue.lock().unwrap().pop_front()
+    }
+}
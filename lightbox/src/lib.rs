@@ -1,101 +1,495 @@
+extern crate chrono;
+#[cfg(feature = "cli")]
+extern crate clap;
 extern crate ctrlc;
 extern crate rpi_ws281x_display;
-extern crate time;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+#[cfg(test)]
+extern crate toml;
 
-use rpi_ws281x_display::PixelDisplay;
-use std::{thread, time as std_time};
+use rpi_ws281x_display::{PixelDisplay, Result};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use rpi_ws281x_display::Animation;
 
-pub struct LightBox<D: PixelDisplay> {
+#[cfg(feature = "cli")]
+pub mod cli;
+mod clock;
+mod command;
+pub mod playlist;
+pub mod schedule;
+
+pub use clock::{Clock, RealClock, ScaledClock, VirtualClock};
+pub use command::{Command, Commands};
+
+/// How many frames in a row `run` lets fail to render before giving up.
+const MAX_RENDER_FAILURES: u32 = 30;
+
+/// Something to call with the light box, see `RunOptions::before_frame`.
+pub type Hook<D, C> = Box<dyn FnMut(&mut LightBox<D, C>)>;
+
+/// What `LightBox::run` does besides playing the playlist.
+pub struct RunOptions<D: PixelDisplay + 'static, C: Clock = RealClock> {
+    /// Handled before each frame, a `Command::Quit` stops the light box.
+    pub commands: Commands,
+    /// Called ahead of every frame, e.g. to show what is playing on the
+    /// display.
+    pub before_frame: Hook<D, C>,
+}
+
+impl<D: PixelDisplay + 'static, C: Clock> Default for RunOptions<D, C> {
+    fn default() -> Self {
+        RunOptions { commands: Commands::new(), before_frame: Box::new(|_| {}) }
+    }
+}
+
+pub struct LightBox<D: PixelDisplay + 'static, C: Clock = RealClock> {
     pub display: D,
-    playlist: Vec<Box<Animation<D>>>,
+    playlist: Vec<Box<dyn Animation>>,
     playlist_idx: usize,
     elapsed: f64,
     last: f64,
     setup: bool,
+    paused: bool,
     fps: u64,
+    clock: C,
 }
 
-impl<D: PixelDisplay> LightBox<D> {
-    pub fn new(pixel_display: D, playlist: Vec<Box<Animation<D>>>, fps: u64) -> Self {
+impl<D: PixelDisplay + 'static> LightBox<D> {
+    pub fn new(pixel_display: D, playlist: Vec<Box<dyn Animation>>, fps: u64) -> Self {
+        LightBox::with_clock(pixel_display, playlist, fps, RealClock::new())
+    }
+}
+
+impl<D: PixelDisplay + 'static, C: Clock> LightBox<D, C> {
+    /// Creates a LightBox whose animations are timed by `clock` instead
+    /// of the wall clock.
+    pub fn with_clock(pixel_display: D, playlist: Vec<Box<dyn Animation>>, fps: u64, clock: C) -> Self {
         LightBox {
             display: pixel_display,
             playlist,
             playlist_idx: 0,
             elapsed: 0.0,
-            last: 0.0,
+            last: clock.now(),
             setup: true,
+            paused: false,
             fps,
+            clock,
         }
     }
 
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
     pub fn reset(&mut self) {
         self.playlist_idx = 0;
         self.elapsed = 0.0;
-        self.last = time::precise_time_s();
+        self.last = self.clock.now();
         self.setup = true;
     }
 
-    pub fn update(&mut self) {
-        let len = self.playlist.len();
-        let now = time::precise_time_s();
+    /// Renders the next frame and then sleeps for whatever is left of it.
+    /// A failed render still takes up its frame so that retrying doesn't
+    /// spin.
+    pub fn update(&mut self) -> Result<()> {
+        let now = self.clock.now();
         let diff = now - self.last;
         self.last = now;
 
+        let advanced = self.step(diff);
+
+        // try to maintain the number of frames per second,
+        // subtract the amount of time it took to do the last animation from
+        // the time allotted to a single frame.
+        let frame_time = 1.0 / self.fps.max(1) as f64;
+        let work_time = self.clock.now() - now;
+        if work_time < frame_time {
+            self.clock.sleep(frame_time - work_time);
+        }
+
+        if let Ok(true) = advanced {
+            self.last = self.clock.now();
+        }
+        advanced.map(|_| ())
+    }
+
+    /// Advances the current animation by `delta` seconds and renders it
+    /// without looking at the clock or sleeping, which makes it possible
+    /// to render a playlist faster (or slower) than real time.
+    ///
+    /// Returns true when the animation finished and the playlist moved on
+    /// to the next one. The animation still advances when rendering fails.
+    pub fn step(&mut self, delta: f64) -> Result<bool> {
+        if self.paused {
+            self.display.render()?;
+            return Ok(false);
+        }
+        self.advance(delta)
+    }
+
+    /// Like `step`, even when paused.
+    fn advance(&mut self, delta: f64) -> Result<bool> {
+        if self.playlist.is_empty() {
+            self.display.render()?;
+            return Ok(false);
+        }
+
         let animation = &mut self.playlist[self.playlist_idx];
         if self.setup {
             animation.setup(&mut self.display);
             self.setup = false;
         }
 
-        animation.update(&mut self.display, diff, self.elapsed);
+        animation.update(&mut self.display, delta, self.elapsed);
 
-        self.elapsed += diff;
-        self.display.render();
+        self.elapsed += delta;
+        let rendered = self.display.render();
 
-        // try to maintain the number of frames per second,
-        // subtract the amount of time it took to do the last animation from
-        // the total time for a specific FPS rate.
-        let to_sleep: u64 = (time::precise_time_s() - now) as u64;
-        // only sleep if to_sleep is less than the allotted FPS.
-        // otherwise we might be sleep for a very long time...
-        if to_sleep <= self.fps {
-            thread::sleep(std_time::Duration::from_millis(self.fps - to_sleep));
+        if animation.is_finished(&mut self.display, self.elapsed) {
+            self.next();
+            return rendered.map(|_| true);
         }
+        rendered.map(|_| false)
+    }
 
-        if animation.is_finished(&mut self.display, self.elapsed) {
-            use std::ops::Rem;
-            self.playlist_idx = (self.playlist_idx + 1).rem(len);
-            self.elapsed = 0.0;
-            self.last = time::precise_time_s();
-            self.setup = true;
+    /// The position in the playlist of the animation that is playing.
+    pub fn current_index(&self) -> usize {
+        self.playlist_idx
+    }
+
+    /// The name of the animation that is playing.
+    pub fn current_name(&self) -> Option<&str> {
+        self.playlist.get(self.playlist_idx).map(|a| a.name())
+    }
+
+    /// The number of seconds the current animation has been playing for.
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn playlist_len(&self) -> usize {
+        self.playlist.len()
+    }
+
+    /// The names of every animation in the playlist, in order.
+    pub fn playlist_names(&self) -> Vec<&str> {
+        self.playlist.iter().map(|a| a.name()).collect()
+    }
+
+    /// Skips to the next animation in the playlist, wrapping around at
+    /// the end.
+    pub fn next(&mut self) {
+        if !self.playlist.is_empty() {
+            let idx = (self.playlist_idx + 1) % self.playlist.len();
+            self.select(idx);
+        }
+    }
+
+    /// Goes back to the previous animation in the playlist, wrapping
+    /// around at the start.
+    pub fn previous(&mut self) {
+        if !self.playlist.is_empty() {
+            let len = self.playlist.len();
+            let idx = (self.playlist_idx + len - 1) % len;
+            self.select(idx);
+        }
+    }
+
+    /// Starts the animation at `index` from the beginning. Returns false
+    /// if there is no such animation.
+    pub fn select(&mut self, index: usize) -> bool {
+        if index >= self.playlist.len() {
+            return false;
+        }
+        self.playlist_idx = index;
+        self.elapsed = 0.0;
+        self.setup = true;
+        true
+    }
+
+    /// Starts the first animation called `name` from the beginning.
+    /// Returns false if the playlist doesn't contain it.
+    pub fn select_by_name(&mut self, name: &str) -> bool {
+        match self.playlist.iter().position(|a| a.name() == name) {
+            Some(index) => self.select(index),
+            None => false,
+        }
+    }
+
+    /// Freezes the current frame. The display keeps being rendered so
+    /// that brightness changes still show up.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Carries out `command`. Stepping renders the frame it moves on to,
+    /// the rest show up on the next update. `Quit` is left to `run`.
+    pub fn handle(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Next => self.next(),
+            Command::Previous => self.previous(),
+            Command::TogglePause if self.paused => self.resume(),
+            Command::TogglePause | Command::Pause => self.pause(),
+            Command::Resume => self.resume(),
+            Command::Step => {
+                self.pause();
+                self.advance(1.0 / self.fps.max(1) as f64)?;
+            }
+            Command::Select(index) => {
+                self.select(index);
+            }
+            Command::AdjustBrightness(change) => {
+                let brightness = i16::from(self.display.get_brightness()).saturating_add(change);
+                self.display.set_brightness(brightness.clamp(0, 255) as u8);
+            }
+            Command::SetBrightness(brightness) => self.display.set_brightness(brightness),
+            Command::Quit => {}
         }
+        Ok(())
+    }
+
+    /// Swaps in a new playlist and starts it from the beginning.
+    pub fn set_playlist(&mut self, playlist: Vec<Box<dyn Animation>>) {
+        self.playlist = playlist;
+        self.reset();
     }
 
-    pub fn clear(&mut self) {
+    pub fn clear(&mut self) -> Result<()> {
         self.display.clear();
-        self.display.render();
+        self.display.render()
     }
 
-    pub fn run_forever(&mut self) {
+    /// Plays the playlist until Ctrl-C is pressed or a `Command::Quit`
+    /// comes in. Frames that fail to render are reported and retried,
+    /// until too many fail in a row.
+    pub fn run(&mut self, options: RunOptions<D, C>) -> Result<()> {
+        let RunOptions { commands, mut before_frame } = options;
         let running = Arc::new(AtomicBool::new(true));
         let r = running.clone();
         ctrlc::set_handler(move || {
             r.store(false, Ordering::SeqCst);
         }).expect("Error setting Ctrl-C handler");
         self.reset();
+        let mut failures = 0;
         while running.load(Ordering::SeqCst) {
-            self.update();
+            let mut result = Ok(());
+            while let Some(command) = commands.pop() {
+                if command == Command::Quit {
+                    return Ok(());
+                }
+                result = result.and(self.handle(command));
+            }
+            before_frame(self);
+            match result.and_then(|_| self.update()) {
+                Ok(()) => failures = 0,
+                Err(e) => {
+                    failures += 1;
+                    if failures >= MAX_RENDER_FAILURES {
+                        return Err(e);
+                    }
+                    println!("Failed to render a frame: {}", e);
+                }
+            }
         }
+        Ok(())
     }
 }
 
-impl<D: PixelDisplay> Drop for LightBox<D> {
+impl<D: PixelDisplay + 'static, C: Clock> Drop for LightBox<D, C> {
     fn drop(&mut self) {
-        self.display.clear();
-        self.display.render();
+        // nothing left to report the error to
+        let _ = self.clear();
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpi_ws281x_display::{Error, MemoryDisplay, Pixel};
+    use playlist::{build_playlist, PlaylistEntry};
+
+    /// Fails to render while `failing` is set.
+    struct FlakyDisplay {
+        display: MemoryDisplay,
+        failing: bool,
+    }
+
+    impl PixelDisplay for FlakyDisplay {
+        fn rows(&self) -> usize {
+            self.display.rows()
+        }
+
+        fn cols(&self) -> usize {
+            self.display.cols()
+        }
+
+        fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
+            self.display.set_at(x, y, pixel)
+        }
+
+        fn get_at(&self, x: usize, y: usize) -> Pixel {
+            self.display.get_at(x, y)
+        }
+
+        fn frame(&self) -> &[Pixel] {
+            self.display.frame()
+        }
+
+        fn frame_mut(&mut self) -> &mut [Pixel] {
+            self.display.frame_mut()
+        }
+
+        fn render(&mut self) -> Result<()> {
+            if self.failing {
+                return Err(Error::backend("unplugged"));
+            }
+            self.display.render()
+        }
+
+        fn clear(&mut self) {
+            self.display.clear()
+        }
+
+        fn get_brightness(&self) -> u8 {
+            self.display.get_brightness()
+        }
+
+        fn set_brightness(&mut self, brightness: u8) {
+            self.display.set_brightness(brightness)
+        }
+    }
+
+    #[test]
+    fn test_virtual_clock_playlist() {
+        let entries = vec![PlaylistEntry::new("basic", Some(20.0)), PlaylistEntry::new("fireflies", Some(40.0))];
+        let playlist = build_playlist(&entries).unwrap();
+        let mut lightbox = LightBox::with_clock(MemoryDisplay::new(7, 7), playlist, 30, VirtualClock::new());
+
+        // 30 seconds at 30 fps
+        for _ in 0..900 {
+            lightbox.update().unwrap();
+        }
+        assert_eq!(lightbox.display.renders(), 900);
+        assert_eq!(lightbox.playlist_idx, 1);
+        assert!((lightbox.clock().now() - 30.0).abs() < 1e-6);
+        assert!((lightbox.elapsed - 10.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_playlist_controls() {
+        let entries = vec![
+            PlaylistEntry::new("basic", None),
+            PlaylistEntry::new("circle", None),
+            PlaylistEntry::new("fireflies", None),
+        ];
+        let playlist = build_playlist(&entries).unwrap();
+        let mut lightbox = LightBox::with_clock(MemoryDisplay::new(7, 7), playlist, 30, VirtualClock::new());
+        assert_eq!(lightbox.playlist_names(), vec!["basic", "circle", "fireflies"]);
+
+        lightbox.previous();
+        assert_eq!(lightbox.current_name(), Some("fireflies"));
+        lightbox.next();
+        assert_eq!(lightbox.current_index(), 0);
+        assert!(lightbox.select_by_name("circle"));
+        assert!(!lightbox.select_by_name("letters"));
+        assert!(!lightbox.select(3));
+        assert_eq!(lightbox.current_index(), 1);
+
+        lightbox.update().unwrap();
+        lightbox.pause();
+        lightbox.update().unwrap();
+        lightbox.update().unwrap();
+        assert_eq!(lightbox.elapsed(), 0.0);
+        lightbox.resume();
+        lightbox.update().unwrap();
+        // only the frame since resuming counts
+        assert!((lightbox.elapsed() - 1.0 / 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_commands() {
+        let entries = vec![PlaylistEntry::new("basic", None), PlaylistEntry::new("circle", None)];
+        let playlist = build_playlist(&entries).unwrap();
+        let mut lightbox = LightBox::with_clock(MemoryDisplay::new(7, 7), playlist, 30, VirtualClock::new());
+
+        lightbox.handle(Command::Next).unwrap();
+        assert_eq!(lightbox.current_index(), 1);
+        lightbox.handle(Command::Select(0)).unwrap();
+        lightbox.handle(Command::Select(5)).unwrap();
+        assert_eq!(lightbox.current_index(), 0);
+        lightbox.handle(Command::Previous).unwrap();
+        assert_eq!(lightbox.current_index(), 1);
+
+        lightbox.handle(Command::TogglePause).unwrap();
+        assert!(lightbox.is_paused());
+        lightbox.handle(Command::Step).unwrap();
+        lightbox.handle(Command::Step).unwrap();
+        lightbox.update().unwrap();
+        assert!(lightbox.is_paused());
+        assert!((lightbox.elapsed() - 2.0 / 30.0).abs() < 1e-9);
+        assert_eq!(lightbox.display.renders(), 3);
+        lightbox.handle(Command::TogglePause).unwrap();
+        assert!(!lightbox.is_paused());
+        lightbox.handle(Command::Resume).unwrap();
+        assert!(!lightbox.is_paused());
+        lightbox.handle(Command::Pause).unwrap();
+        lightbox.handle(Command::Pause).unwrap();
+        assert!(lightbox.is_paused());
+
+        lightbox.handle(Command::AdjustBrightness(-200)).unwrap();
+        assert_eq!(lightbox.display.get_brightness(), 55);
+        lightbox.handle(Command::AdjustBrightness(-100)).unwrap();
+        assert_eq!(lightbox.display.get_brightness(), 0);
+        lightbox.handle(Command::AdjustBrightness(300)).unwrap();
+        assert_eq!(lightbox.display.get_brightness(), 255);
+        lightbox.handle(Command::AdjustBrightness(i16::MAX)).unwrap();
+        assert_eq!(lightbox.display.get_brightness(), 255);
+        lightbox.handle(Command::AdjustBrightness(i16::MIN)).unwrap();
+        assert_eq!(lightbox.display.get_brightness(), 0);
+        lightbox.handle(Command::SetBrightness(10)).unwrap();
+        assert_eq!(lightbox.display.get_brightness(), 10);
+    }
+
+    #[test]
+    fn test_render_failures_are_reported() {
+        let playlist = build_playlist(&[PlaylistEntry::new("basic", None)]).unwrap();
+        let display = FlakyDisplay { display: MemoryDisplay::new(7, 7), failing: true };
+        let mut lightbox = LightBox::with_clock(display, playlist, 30, VirtualClock::new());
+
+        let err = lightbox.update().unwrap_err();
+        assert_eq!(err.to_string(), "display error: unplugged");
+        // the failed frame still took up its time
+        assert!((lightbox.clock().now() - 1.0 / 30.0).abs() < 1e-9);
+
+        lightbox.display.failing = false;
+        lightbox.update().unwrap();
+        assert_eq!(lightbox.display.display.renders(), 1);
+        assert!((lightbox.elapsed() - 1.0 / 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_boxed_display() {
+        let playlist = build_playlist(&[PlaylistEntry::new("circle", None)]).unwrap();
+        let display: Box<dyn PixelDisplay> = Box::new(MemoryDisplay::new(7, 7));
+        let mut lightbox = LightBox::with_clock(display, playlist, 30, VirtualClock::new());
+        lightbox.update().unwrap();
+        lightbox.update().unwrap();
+        assert_eq!(lightbox.display.rows(), 7);
+        assert_eq!(lightbox.current_name(), Some("circle"));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{error, fmt};
+use std::str::FromStr;
+
+use rpi_ws281x_display::{animations, Animation};
+use rpi_ws281x_display::animations::timed::Timed;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlaylistError {
+    UnknownAnimation(String),
+    InvalidDuration(String),
+}
+
+impl fmt::Display for PlaylistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlaylistError::UnknownAnimation(ref name) => write!(
+                f, "unknown animation {:?}, expected one of: {}", name, animations::NAMES.join(", ")
+            ),
+            PlaylistError::InvalidDuration(ref duration) => write!(
+                f, "invalid duration {:?}, expected a number of seconds", duration
+            ),
+        }
+    }
+}
+
+impl error::Error for PlaylistError {
+    fn description(&self) -> &str {
+        match *self {
+            PlaylistError::UnknownAnimation(_) => "unknown animation",
+            PlaylistError::InvalidDuration(_) => "invalid duration",
+        }
+    }
+}
+
+/// A single playlist item: one of the built in animations by name and,
+/// optionally, how many seconds to show it for. Without a duration the
+/// animation decides for itself when it is finished.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub name: String,
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+impl PlaylistEntry {
+    pub fn new<S: Into<String>>(name: S, duration: Option<f64>) -> Self {
+        PlaylistEntry { name: name.into(), duration }
+    }
+
+    /// Checks that the animation exists and that the duration makes sense
+    /// without building anything.
+    pub fn validate(&self) -> Result<(), PlaylistError> {
+        if animations::NAMES.iter().all(|n| *n != self.name) {
+            return Err(PlaylistError::UnknownAnimation(self.name.clone()));
+        }
+        match self.duration {
+            Some(d) if d.is_nan() || d <= 0.0 => Err(PlaylistError::InvalidDuration(d.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn build(&self) -> Result<Box<dyn Animation>, PlaylistError> {
+        let animation = animations::by_name(&self.name)
+            .ok_or_else(|| PlaylistError::UnknownAnimation(self.name.clone()))?;
+        Ok(match self.duration {
+            Some(duration) => Box::new(Timed::new(animation, duration)),
+            None => animation,
+        })
+    }
+}
+
+/// Parses `name` or `name:seconds`, e.g. `fireflies:30`.
+impl FromStr for PlaylistEntry {
+    type Err = PlaylistError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let duration = match parts.next() {
+            Some(d) => Some(d.trim().parse::<f64>().map_err(|_| PlaylistError::InvalidDuration(d.to_string()))?),
+            None => None,
+        };
+        let entry = PlaylistEntry::new(name, duration);
+        entry.validate()?;
+        Ok(entry)
+    }
+}
+
+/// Builds every entry of a playlist, failing on the first unknown name.
+pub fn build_playlist(entries: &[PlaylistEntry]) -> Result<Vec<Box<dyn Animation>>, PlaylistError> {
+    entries.iter().map(|entry| entry.build()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        assert_eq!("fireflies".parse(), Ok(PlaylistEntry::new("fireflies", None)));
+        assert_eq!("circle:2.5".parse(), Ok(PlaylistEntry::new("circle", Some(2.5))));
+        assert_eq!(
+            "nope:3".parse::<PlaylistEntry>(),
+            Err(PlaylistError::UnknownAnimation("nope".to_string()))
+        );
+        assert_eq!(
+            "basic:soon".parse::<PlaylistEntry>(),
+            Err(PlaylistError::InvalidDuration("soon".to_string()))
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::{error, fmt};
+use std::sync::Arc;
+
+use chrono::{Datelike, Duration, Local, NaiveDateTime, NaiveTime, Weekday};
+
+use playlist::{PlaylistEntry, PlaylistError};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScheduleError {
+    InvalidBrightness(u8),
+    Playlist(PlaylistError),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScheduleError::InvalidBrightness(b) => write!(
+                f, "invalid brightness {}, expected a percentage from 0 to 100", b
+            ),
+            ScheduleError::Playlist(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ScheduleError {
+    fn description(&self) -> &str {
+        match *self {
+            ScheduleError::InvalidBrightness(_) => "invalid brightness",
+            ScheduleError::Playlist(_) => "invalid playlist",
+        }
+    }
+}
+
+impl From<PlaylistError> for ScheduleError {
+    fn from(e: PlaylistError) -> Self {
+        ScheduleError::Playlist(e)
+    }
+}
+
+/// The settings a rule changes. Anything left out is left alone.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<bool>,
+    /// As a percentage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playlist: Option<Vec<PlaylistEntry>>,
+}
+
+impl Settings {
+    pub fn is_empty(&self) -> bool {
+        self.power.is_none() && self.brightness.is_none() && self.playlist.is_none()
+    }
+
+    /// Takes on every setting that `other` has.
+    fn merge(&mut self, other: &Settings) {
+        if other.power.is_some() {
+            self.power = other.power;
+        }
+        if other.brightness.is_some() {
+            self.brightness = other.brightness;
+        }
+        if other.playlist.is_some() {
+            self.playlist = other.playlist.clone();
+        }
+    }
+}
+
+/// Changes some settings every day at a time of day, or only on some
+/// days of the week.
+///
+/// ```toml
+/// [[rules]]
+/// days = ["sat", "sun"]
+/// at = "09:00"
+/// playlist = [{ name = "circle" }]
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Every day when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    pub at: NaiveTime,
+    #[serde(flatten)]
+    pub settings: Settings,
+}
+
+impl Rule {
+    pub fn new(at: NaiveTime, settings: Settings) -> Self {
+        Rule { days: Vec::new(), at, settings }
+    }
+
+    pub fn on(mut self, days: &[Weekday]) -> Self {
+        self.days = days.to_vec();
+        self
+    }
+
+    pub fn applies_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    pub fn validate(&self) -> Result<(), ScheduleError> {
+        if let Some(brightness) = self.settings.brightness {
+            if brightness > 100 {
+                return Err(ScheduleError::InvalidBrightness(brightness));
+            }
+        }
+        if let Some(ref playlist) = self.settings.playlist {
+            for entry in playlist.iter() {
+                entry.validate()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl Schedule {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Schedule { rules }
+    }
+
+    pub fn validate(&self) -> Result<(), ScheduleError> {
+        self.rules.iter().try_for_each(|rule| rule.validate())
+    }
+
+    /// What the rules say the settings should be at `now`: for each
+    /// setting, the value of the rule that fired most recently. Rules
+    /// repeat at least weekly, so looking back a week is enough. When two
+    /// rules fire at once the later one in the list wins.
+    pub fn state_at(&self, now: NaiveDateTime) -> Settings {
+        self.fired_between(now - Duration::weeks(1), now)
+    }
+
+    /// The settings of every rule that fired after `since` and up to and
+    /// including `now`, applied in order.
+    pub fn fired_between(&self, since: NaiveDateTime, now: NaiveDateTime) -> Settings {
+        let since = since.max(now - Duration::weeks(1));
+        let mut fired: Vec<(NaiveDateTime, &Rule)> = Vec::new();
+        let mut date = since.date();
+        while date <= now.date() {
+            for rule in self.rules.iter().filter(|rule| rule.applies_on(date.weekday())) {
+                let at = date.and_time(rule.at);
+                if since < at && at <= now {
+                    fired.push((at, rule));
+                }
+            }
+            date = date.succ_opt().unwrap();
+        }
+        fired.sort_by_key(|&(at, _)| at);
+
+        let mut settings = Settings::default();
+        for (_, rule) in fired {
+            settings.merge(&rule.settings);
+        }
+        settings
+    }
+}
+
+/// Where the scheduler gets the local time from.
+pub trait WallClock {
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// The system's local time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalClock;
+
+impl WallClock for LocalClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// A fixed point in time, mostly for tests.
+impl WallClock for NaiveDateTime {
+    fn now(&self) -> NaiveDateTime {
+        *self
+    }
+}
+
+/// Follows a schedule, reporting settings only as their rules fire so
+/// that changes made by hand in between stick until the next rule says
+/// otherwise.
+pub struct Scheduler<W: WallClock = LocalClock> {
+    schedule: Arc<Schedule>,
+    clock: W,
+    last: Option<NaiveDateTime>,
+}
+
+impl Scheduler {
+    pub fn new(schedule: Schedule) -> Self {
+        Scheduler::with_clock(schedule, LocalClock)
+    }
+}
+
+impl<W: WallClock> Scheduler<W> {
+    pub fn with_clock(schedule: Schedule, clock: W) -> Self {
+        Scheduler { schedule: Arc::new(schedule), clock, last: None }
+    }
+
+    /// The rules being followed, shared so that handing out copies of
+    /// them is cheap.
+    pub fn schedule(&self) -> &Arc<Schedule> {
+        &self.schedule
+    }
+
+    /// Replaces the rules. The next poll applies everything the new rules
+    /// say, as on startup.
+    pub fn set_schedule(&mut self, schedule: Schedule) {
+        self.schedule = Arc::new(schedule);
+        self.last = None;
+    }
+
+    pub fn clock_mut(&mut self) -> &mut W {
+        &mut self.clock
+    }
+
+    /// Makes the next poll only report rules that fire from now on, e.g.
+    /// when the settings were restored from before a restart and the
+    /// scheduled state would overwrite them.
+    pub fn skip_to_now(&mut self) {
+        self.last = Some(self.clock.now());
+    }
+
+    /// Returns the settings of the rules that fired since the last poll,
+    /// if any. The first poll returns the whole scheduled state, as does
+    /// one after the clock went backwards.
+    pub fn poll(&mut self) -> Option<Settings> {
+        let now = self.clock.now();
+        let settings = match self.last {
+            Some(last) if last <= now => self.schedule.fired_between(last, now),
+            _ => self.schedule.state_at(now),
+        };
+        self.last = Some(now);
+        if settings.is_empty() {
+            None
+        } else {
+            Some(settings)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        // 2024-01-01 was a Monday
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(hour, min, 0).unwrap()
+    }
+
+    fn time(hour: u32, min: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, min, 0).unwrap()
+    }
+
+    fn schedule() -> Schedule {
+        let weekend = vec![PlaylistEntry::new("circle", None)];
+        let weekday = vec![PlaylistEntry::new("fireflies", None)];
+        Schedule::new(vec![
+            Rule::new(time(18, 0), Settings { power: Some(true), brightness: Some(100), ..Settings::default() }),
+            Rule::new(time(22, 0), Settings { brightness: Some(20), ..Settings::default() }),
+            Rule::new(time(0, 0), Settings { power: Some(false), ..Settings::default() }),
+            Rule::new(time(0, 0), Settings { playlist: Some(weekend), ..Settings::default() })
+                .on(&[Weekday::Sat, Weekday::Sun]),
+            Rule::new(time(0, 0), Settings { playlist: Some(weekday), ..Settings::default() })
+                .on(&[Weekday::Mon]),
+        ])
+    }
+
+    #[test]
+    fn test_state_at() {
+        let schedule = schedule();
+        let state = schedule.state_at(at(3, 12, 0));
+        assert_eq!(state.power, Some(false));
+        assert_eq!(state.brightness, Some(20));
+        assert_eq!(state.playlist.unwrap()[0].name, "fireflies");
+
+        let state = schedule.state_at(at(6, 23, 0));
+        assert_eq!(state.power, Some(true));
+        assert_eq!(state.brightness, Some(20));
+        assert_eq!(state.playlist.unwrap()[0].name, "circle");
+    }
+
+    #[test]
+    fn test_scheduler_reports_changes() {
+        let mut scheduler = Scheduler::with_clock(schedule(), at(1, 12, 0));
+        assert_eq!(scheduler.poll().unwrap().power, Some(false));
+        assert_eq!(scheduler.poll(), None);
+
+        *scheduler.clock_mut() = at(1, 18, 0);
+        assert_eq!(
+            scheduler.poll(),
+            Some(Settings { power: Some(true), brightness: Some(100), playlist: None })
+        );
+
+        *scheduler.clock_mut() = at(1, 22, 30);
+        assert_eq!(scheduler.poll(), Some(Settings { brightness: Some(20), ..Settings::default() }));
+
+        // turning off at midnight happens every day, even though the box
+        // was already scheduled to be off the night before
+        *scheduler.clock_mut() = at(2, 0, 0);
+        assert_eq!(scheduler.poll().unwrap().power, Some(false));
+    }
+
+    #[test]
+    fn test_skip_to_now() {
+        let mut scheduler = Scheduler::with_clock(schedule(), at(1, 12, 0));
+        scheduler.skip_to_now();
+        assert_eq!(scheduler.poll(), None);
+
+        *scheduler.clock_mut() = at(1, 18, 0);
+        assert_eq!(scheduler.poll().unwrap().power, Some(true));
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let text = "[[rules]]\ndays = [\"sat\", \"sun\"]\nat = \"22:00\"\nbrightness = 20\n";
+        let schedule: Schedule = ::toml::from_str(text).unwrap();
+        assert_eq!(schedule.rules[0].days, vec![Weekday::Sat, Weekday::Sun]);
+        assert_eq!(schedule.rules[0].at, time(22, 0));
+        assert!(schedule.validate().is_ok());
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::{App, Arg, ArgMatches};
+use rs_ws281x::{ParseStripTypeError, StripType};
+use toml;
+
+use display::Mapping;
+
+/// How this particular box is wired up, read from an optional TOML file
+/// and then overridden by command line flags.
+///
+/// ```toml
+/// rows = 8
+/// cols = 16
+/// mapping = "progressive"
+/// strip = "ws2811-grb"
+/// port = 8080
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub rows: usize,
+    pub cols: usize,
+    pub mapping: Mapping,
+    pub pin: i32,
+    pub dma: i32,
+    pub freq: u32,
+    pub strip: String,
+    pub invert: bool,
+    pub fps: u64,
+    pub address: String,
+    pub port: u16,
+    /// A JSON playlist, as accepted by `PUT /playlist`, to start with
+    /// instead of the saved one.
+    pub playlist: Option<PathBuf>,
+    pub schedule: PathBuf,
+    pub state: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            rows: 7,
+            cols: 7,
+            mapping: Mapping::Serpentine,
+            pin: 18,
+            dma: 10,
+            freq: 800_000,
+            strip: "ws2811-rgb".to_string(),
+            invert: false,
+            fps: 30,
+            address: "0.0.0.0".to_string(),
+            port: 8000,
+            playlist: None,
+            schedule: PathBuf::from("schedule.toml"),
+            state: PathBuf::from("state.json"),
+        }
+    }
+}
+
+fn set<T: FromStr>(matches: &ArgMatches, name: &str, field: &mut T) -> Result<(), String> {
+    if let Some(value) = matches.value_of(name) {
+        *field = value.parse().map_err(|_| format!("invalid value {:?} for --{}", value, name))?;
+    }
+    Ok(())
+}
+
+impl Config {
+    pub fn from_args() -> Result<Config, String> {
+        let matches = App::new("neopixels")
+            .about("Runs a light box and serves its HTTP API")
+            .arg(Arg::with_name("config").short("c").long("config").takes_value(true)
+                .help("TOML file with any of the settings below, flags take precedence"))
+            .arg(Arg::with_name("rows").long("rows").takes_value(true))
+            .arg(Arg::with_name("cols").long("cols").takes_value(true))
+            .arg(Arg::with_name("mapping").long("mapping").takes_value(true)
+                .possible_values(&["serpentine", "progressive"])
+                .help("How the strip runs through the grid"))
+            .arg(Arg::with_name("pin").long("pin").takes_value(true).help("GPIO pin driving the strip"))
+            .arg(Arg::with_name("dma").long("dma").takes_value(true))
+            .arg(Arg::with_name("freq").long("freq").takes_value(true).help("Signal frequency in Hz"))
+            .arg(Arg::with_name("strip").long("strip").takes_value(true)
+                .help("Strip type and color order, e.g. ws2811-grb or sk6812-rgbw"))
+            .arg(Arg::with_name("invert").long("invert").help("Invert the signal, e.g. for an inverting level shifter"))
+            .arg(Arg::with_name("fps").long("fps").takes_value(true))
+            .arg(Arg::with_name("address").long("address").takes_value(true).help("Address to serve HTTP on"))
+            .arg(Arg::with_name("port").short("p").long("port").takes_value(true))
+            .arg(Arg::with_name("playlist").long("playlist").takes_value(true)
+                .help("JSON playlist to start with instead of the saved one"))
+            .arg(Arg::with_name("schedule").long("schedule").takes_value(true))
+            .arg(Arg::with_name("state").long("state").takes_value(true)
+                .help("Where settings are saved between restarts"))
+            .get_matches();
+
+        let mut config = match matches.value_of("config") {
+            Some(path) => {
+                let text = fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path, e))?;
+                toml::from_str(&text).map_err(|e| format!("invalid config {}: {}", path, e))?
+            }
+            None => Config::default(),
+        };
+        set(&matches, "rows", &mut config.rows)?;
+        set(&matches, "cols", &mut config.cols)?;
+        set(&matches, "mapping", &mut config.mapping)?;
+        set(&matches, "pin", &mut config.pin)?;
+        set(&matches, "dma", &mut config.dma)?;
+        set(&matches, "freq", &mut config.freq)?;
+        set(&matches, "strip", &mut config.strip)?;
+        set(&matches, "fps", &mut config.fps)?;
+        set(&matches, "address", &mut config.address)?;
+        set(&matches, "port", &mut config.port)?;
+        set(&matches, "schedule", &mut config.schedule)?;
+        set(&matches, "state", &mut config.state)?;
+        if let Some(path) = matches.value_of("playlist") {
+            config.playlist = Some(PathBuf::from(path));
+        }
+        if matches.is_present("invert") {
+            config.invert = true;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.rows == 0 || self.cols == 0 {
+            return Err("rows and cols must be greater than 0".to_string());
+        }
+        if self.fps == 0 {
+            return Err("fps must be greater than 0".to_string());
+        }
+        self.strip_type()?;
+        Ok(())
+    }
+
+    pub fn strip_type(&self) -> Result<StripType, String> {
+        self.strip.parse().map_err(|e: ParseStripTypeError| e.to_string())
+    }
+}
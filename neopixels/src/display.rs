@@ -1,13 +1,38 @@
+use std::str::FromStr;
+
 use rs_ws281x::{Controller, ControllerBuilder, ChannelBuilder};
 use rs_ws281x::StripType;
 
-use rpi_ws281x_display::{PixelDisplay, Pixel};
+use rpi_ws281x_display::{Error, PixelDisplay, Pixel, Result};
+
+/// How the LED strip is laid out across the grid, starting top left.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mapping {
+    /// Every other row runs right to left.
+    Serpentine,
+    /// Every row runs left to right.
+    Progressive,
+}
+
+impl FromStr for Mapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        match s {
+            "serpentine" => Ok(Mapping::Serpentine),
+            "progressive" => Ok(Mapping::Progressive),
+            _ => Err(format!("unknown mapping {:?}, expected serpentine or progressive", s)),
+        }
+    }
+}
 
 pub struct GridDisplayBuilder {
     cb: ControllerBuilder,
     chb: ChannelBuilder,
     rows: usize,
     cols: usize,
+    mapping: Mapping,
 }
 
 impl GridDisplayBuilder {
@@ -17,6 +42,7 @@ impl GridDisplayBuilder {
             chb: ChannelBuilder::new(),
             rows: 0,
             cols: 0,
+            mapping: Mapping::Serpentine,
         }
     }
 
@@ -30,6 +56,11 @@ impl GridDisplayBuilder {
         self
     }
 
+    pub fn mapping(&mut self, mapping: Mapping) -> &mut Self {
+        self.mapping = mapping;
+        self
+    }
+
     pub fn freq(&mut self, freq: u32) -> &mut Self {
         self.cb.freq(freq);
         self
@@ -60,35 +91,52 @@ impl GridDisplayBuilder {
         self
     }
 
-    pub fn build(&mut self) -> GridDisplay {
+    pub fn build(&mut self) -> Result<GridDisplay> {
         let led_count = (self.rows * self.cols) as i32;
         self.chb.count(led_count);
         self.cb.channel(0, self.chb.build());
 
-        GridDisplay {
-            controller: self.cb.build().expect("Failed to create controller"),
+        let mut order = Vec::with_capacity(self.rows * self.cols);
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                order.push(led_index(self.mapping, self.cols, x, y));
+            }
+        }
+
+        Ok(GridDisplay {
+            controller: self.cb.build().map_err(Error::backend)?,
             rows: self.rows,
             cols: self.cols,
-        }
+            frame: vec![Pixel::default(); self.rows * self.cols],
+            order,
+        })
     }
 }
 
 
+fn led_index(mapping: Mapping, cols: usize, x: usize, y: usize) -> usize {
+    let is_reversed = mapping == Mapping::Serpentine && y % 2 != 0;
+    if is_reversed {
+        return ((y + 1) * cols) - x - 1;
+    }
+    (y * cols) + x
+}
+
+/// Drawing happens on a copy of the frame in grid order, which is only
+/// laid out along the strip when it is rendered.
 pub struct GridDisplay {
     controller: Controller,
     pub rows: usize,
     pub cols: usize,
+    frame: Vec<Pixel>,
+    /// Where each pixel of `frame` is on the strip.
+    order: Vec<usize>,
 }
 
 impl GridDisplay {
-    fn x_y_to_idx<T: Into<usize>>(&self, x: T, y: T) -> usize {
-        let row = y.into();
-        let col = x.into();
-        let is_reversed = row % 2 != 0;
-        if is_reversed {
-            return (((row + 1) * self.rows) - col - 1).into();
-        }
-        ((row * self.rows) + col).into()
+    /// The LED driver, e.g. to look at what the mock one was sent.
+    pub fn controller(&self) -> &Controller {
+        &self.controller
     }
 }
 
@@ -101,32 +149,36 @@ impl PixelDisplay for GridDisplay {
         return self.cols;
     }
 
-    fn set_at<T: Into<usize>>(&mut self, x: T, y: T, pixel: Pixel) {
-        let (x, y) = (x.into(), y.into());
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
         if x < self.cols && y < self.rows {
-            let idx = self.x_y_to_idx(x, y);
-            let leds = self.controller.leds_mut(0);
-            leds[idx] = [pixel.b(), pixel.g(), pixel.r(), pixel.w()];
+            self.frame[x + y * self.cols] = pixel;
         }
     }
 
-    fn get_at<T: Into<usize>>(&self, x: T, y: T) -> Pixel {
-        let idx = self.x_y_to_idx(x.into(), y.into());
-        let leds = self.controller.leds(0);
-        let rc = leds[idx];
-        Pixel::new(rc[2], rc[1], rc[0], rc[3])
+    fn get_at(&self, x: usize, y: usize) -> Pixel {
+        self.frame[x + y * self.cols]
     }
 
-    fn render(&mut self) {
-        self.controller.render();
+    fn frame(&self) -> &[Pixel] {
+        &self.frame
     }
 
-    fn clear(&mut self) {
-        for col in 0..self.cols {
-            for row in 0..self.rows {
-                self.set_at(col, row, (0).into());
+    fn frame_mut(&mut self) -> &mut [Pixel] {
+        &mut self.frame
+    }
+
+    fn render(&mut self) -> Result<()> {
+        {
+            let leds = self.controller.leds_mut(0);
+            for (pixel, &idx) in self.frame.iter().zip(self.order.iter()) {
+                leds[idx] = [pixel.b(), pixel.g(), pixel.r(), pixel.w()];
             }
         }
+        self.controller.render().map_err(Error::backend)
+    }
+
+    fn clear(&mut self) {
+        self.fill((0).into());
     }
 
     fn get_brightness(&self) -> u8 {
@@ -137,3 +189,40 @@ impl PixelDisplay for GridDisplay {
         self.controller.set_brightness(0, brightness);
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+
+    fn grid(mapping: Mapping) -> GridDisplay {
+        GridDisplayBuilder::new()
+            .rows(2)
+            .cols(2)
+            .mapping(mapping)
+            .gpio_pin(18)
+            .brightness(255)
+            .strip_type(StripType::Ws2811Grb)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render_follows_mapping() {
+        let mut display = grid(Mapping::Serpentine);
+        display.set_at(0, 1, Pixel::rgb(255, 0, 0));
+        display.set_at(1, 1, Pixel::rgb(0, 0, 255));
+        display.render().unwrap();
+        assert_eq!(display.get_at(0, 1), Pixel::rgb(255, 0, 0));
+        assert_eq!(display.controller.device().wire(0), &[0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 255, 0]);
+
+        let mut display = grid(Mapping::Progressive);
+        display.set_at(0, 1, Pixel::rgb(255, 0, 0));
+        display.render().unwrap();
+        assert_eq!(&display.controller.device().wire(0)[6..9], &[0, 255, 0]);
+    }
+
+    #[test]
+    fn test_illegal_pin() {
+        assert!(GridDisplayBuilder::new().rows(1).cols(1).gpio_pin(4).build().is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use base64;
+use rpi_ws281x_display::Pixel;
+
+use render::Region;
+
+/// How long pushed pixels stay up when a request doesn't say.
+pub const DEFAULT_TIMEOUT: f64 = 30.0;
+
+/// The longest pushed pixels may stay up for, a day.
+pub const MAX_TIMEOUT: f64 = 24.0 * 60.0 * 60.0;
+
+/// The number of seconds pushed pixels stay up for, given the `timeout`
+/// of a request.
+pub fn timeout(requested: Option<f64>) -> Result<f64, String> {
+    match requested {
+        None => Ok(DEFAULT_TIMEOUT),
+        Some(timeout) if (0.0..=MAX_TIMEOUT).contains(&timeout) => Ok(timeout),
+        Some(timeout) => Err(format!("expected a timeout from 0 to {} seconds, got {}", MAX_TIMEOUT, timeout)),
+    }
+}
+
+/// A whole frame, either as a list of colors or as base64 encoded RGB or
+/// RGBW bytes, in row-major order.
+///
+/// ```json
+/// {"pixels": ["#ff0000", [0, 255, 0], ...], "timeout": 10}
+/// {"data": "/wAAAP8A...", "timeout": 10}
+/// ```
+#[derive(Deserialize)]
+pub struct FrameRequest {
+    pub pixels: Option<Vec<Pixel>>,
+    pub data: Option<String>,
+    pub timeout: Option<f64>,
+}
+
+impl FrameRequest {
+    /// Decodes the frame for a display with `count` LEDs.
+    pub fn decode(&self, count: usize) -> Result<Vec<Pixel>, String> {
+        let pixels = match (self.pixels.as_ref(), self.data.as_ref()) {
+            (Some(pixels), None) => pixels.clone(),
+            (None, Some(data)) => {
+                let bytes = base64::decode(data).map_err(|e| format!("invalid base64 data: {}", e))?;
+                let channels = if bytes.len() == count * 3 {
+                    3
+                } else if bytes.len() == count * 4 {
+                    4
+                } else {
+                    return Err(format!(
+                        "expected {} bytes of RGB or {} bytes of RGBW data, got {}",
+                        count * 3, count * 4, bytes.len()
+                    ));
+                };
+                bytes.chunks(channels)
+                    .map(|c| Pixel::new(c[0], c[1], c[2], if channels == 4 { c[3] } else { 0 }))
+                    .collect()
+            }
+            _ => return Err("expected exactly one of \"pixels\" or \"data\"".to_string()),
+        };
+        if pixels.len() != count {
+            return Err(format!("expected {} pixels, got {}", count, pixels.len()));
+        }
+        Ok(pixels)
+    }
+}
+
+/// Fills a rectangle, or the whole display when it is left out.
+#[derive(Deserialize)]
+pub struct FillRequest {
+    pub color: Pixel,
+    #[serde(default)]
+    pub x: usize,
+    #[serde(default)]
+    pub y: usize,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub timeout: Option<f64>,
+}
+
+impl FillRequest {
+    pub fn region(&self, rows: usize, cols: usize) -> Region {
+        Region {
+            x: self.x,
+            y: self.y,
+            width: self.width.unwrap_or_else(|| cols.saturating_sub(self.x)),
+            height: self.height.unwrap_or_else(|| rows.saturating_sub(self.y)),
+        }
+    }
+}
+
+/// Sets a single LED.
+#[derive(Deserialize)]
+pub struct PixelRequest {
+    pub x: usize,
+    pub y: usize,
+    pub color: Pixel,
+    pub timeout: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_base64() {
+        let rgb = FrameRequest { pixels: None, data: Some("/wAAAP8A".to_string()), timeout: None };
+        assert_eq!(rgb.decode(2), Ok(vec![Pixel::rgb(255, 0, 0), Pixel::rgb(0, 255, 0)]));
+        assert!(rgb.decode(3).is_err());
+
+        let rgbw = FrameRequest { pixels: None, data: Some("AAAA/w==".to_string()), timeout: None };
+        assert_eq!(rgbw.decode(1), Ok(vec![Pixel::new(0u8, 0, 0, 255)]));
+    }
+
+    #[test]
+    fn test_timeout() {
+        assert_eq!(timeout(None), Ok(DEFAULT_TIMEOUT));
+        assert_eq!(timeout(Some(0.5)), Ok(0.5));
+        assert!(timeout(Some(1e300)).is_err());
+        assert!(timeout(Some(-1.0)).is_err());
+        assert!(timeout(Some(f64::NAN)).is_err());
+    }
+}
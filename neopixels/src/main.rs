@@ -1,92 +1,265 @@
-#![feature(plugin)]
-
 #![feature(proc_macro_hygiene, decl_macro)]
 #[macro_use] extern crate rocket;
-extern crate rocket_contrib;
+#[macro_use] extern crate rocket_contrib;
+extern crate rocket_cors;
+extern crate clap;
+extern crate base64;
+extern crate serde;
+extern crate serde_json;
+extern crate toml;
+#[macro_use] extern crate serde_derive;
 
 extern crate rpi_ws281x_display;
 extern crate lightbox;
 extern crate rs_ws281x;
 
+mod config;
 mod display;
+mod frame;
+mod render;
+mod state;
+mod stream;
+use config::Config;
 use display::{GridDisplay, GridDisplayBuilder};
+use frame::{self, FillRequest, FrameRequest, PixelRequest};
+use render::{Command, Region, RenderThread};
+use state::StateFile;
+use stream::{Broadcast, FrameBroadcaster, Subscription, MAX_SUBSCRIBERS};
 
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::thread::Thread;
-use std::sync::atomic::AtomicPtr;
-use std::ops::Deref;
+use std::fs;
+use std::path::Path;
+use std::process;
 
-use rocket_contrib::Json;
+use rocket_contrib::json::{Json, JsonValue};
 
 use rocket::State;
-use rs_ws281x::StripType;
-use rpi_ws281x_display::PixelDisplay;
-
-//use rpi_ws281x_display::animations::snider::{CircleAnimation, StripeAnimation, BasicAnimation};
-use rpi_ws281x_display::animations::aaron::Fireflies;
-use lightbox::LightBox;
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering;
-use std::ops::DerefMut;
+use rocket::config::Environment;
+use rocket::response::content;
+use rocket::http::Status;
+use rocket::response::status::{self, BadRequest, NotFound};
+
+use lightbox::{Command as LightBoxCommand, LightBox};
+use lightbox::playlist::PlaylistEntry;
+use lightbox::schedule::Schedule;
 use rocket::http::Method;
 use rocket_cors::{AllowedOrigins, CorsOptions};
 
-type ManagerState<'a> = State<'a, Arc<Mutex<Manager>>>;
+type RenderState<'a> = State<'a, RenderThread>;
 
 #[get("/")]
 fn index() -> &'static str {
     "Hello, world!"
 }
 
+/// What the web UI needs to know about the LEDs themselves.
+#[get("/display")]
+fn display_info(renderer: RenderState, config: State<Config>) -> JsonValue {
+    let status = renderer.status();
+    let strip = config.strip_type().unwrap();
+    json!({
+        "rows": status.rows,
+        "cols": status.cols,
+        "strip": strip.to_string(),
+        "channels": strip.channel_count(),
+        "white": strip.has_white(),
+        "error": status.error,
+    })
+}
+
 #[get("/power/status")]
-fn power_status(manager: ManagerState) -> Json {
-    return Json(json!({
-        "status": manager.lock().unwrap().running.load(Ordering::SeqCst),
-    }));
+fn power_status(renderer: RenderState) -> JsonValue {
+    json!({
+        "status": renderer.status().running,
+    })
 }
 
 #[post("/power/update/<on_off>")]
-fn set_power(on_off: bool, manager: ManagerState) -> Json {
-    let manager_t = manager.clone();
-    let manager = manager.lock().unwrap();
-    if on_off {
-        manager.running.store(true, Ordering::SeqCst);
-        let running_t = manager.running.clone();
-        thread::spawn(move || {
-            {
-                manager_t.lock().unwrap().lightbox.reset();
-            }
-            while running_t.load(Ordering::SeqCst) {
-                manager_t.lock().unwrap().lightbox.update();
-            }
-            manager_t.lock().unwrap().lightbox.clear();
-        });
-
-    } else {
-        manager.running.store(false, Ordering::SeqCst);
-    }
-    return Json(json!({
+fn set_power(on_off: bool, renderer: RenderState) -> JsonValue {
+    renderer.send(Command::Power(on_off));
+    json!({
         "status": on_off,
-    }));
+    })
 }
 
 #[get("/brightness/status")]
-fn brightness_status(manager: ManagerState) -> Json {
-    let brightness = manager.lock().unwrap().lightbox.display.get_brightness();
-    let percentage = brightness_to_percentage(brightness);
-    return Json(json!({
+fn brightness_status(renderer: RenderState) -> JsonValue {
+    let percentage = brightness_to_percentage(renderer.status().brightness);
+    json!({
         "status": percentage,
-    }));
+    })
 }
 
 #[post("/brightness/update/<percentage>")]
-fn set_brightness(percentage: u8, manager: ManagerState) -> Json {
-    let brightness = percentage_to_brightness(percentage);
-    manager.lock().unwrap().lightbox.display.set_brightness(brightness);
-    return Json(json!({
+fn set_brightness(percentage: u8, renderer: RenderState) -> JsonValue {
+    renderer.send(Command::LightBox(LightBoxCommand::SetBrightness(percentage_to_brightness(percentage))));
+    json!({
         "status": percentage,
-    }));
+    })
+}
+
+#[get("/playlist")]
+fn playlist(renderer: RenderState) -> JsonValue {
+    let status = renderer.status();
+    json!({
+        "animations": status.playlist,
+        "current": status.animation,
+    })
+}
+
+#[get("/playlist/current")]
+fn playlist_current(renderer: RenderState) -> JsonValue {
+    let status = renderer.status();
+    json!({
+        "index": status.animation,
+        "name": status.playlist.get(status.animation),
+        "elapsed": status.elapsed,
+        "paused": status.paused,
+    })
+}
+
+#[put("/playlist", format = "json", data = "<entries>")]
+fn set_playlist(entries: Json<Vec<PlaylistEntry>>, renderer: RenderState) -> Result<JsonValue, BadRequest<JsonValue>> {
+    let entries = entries.into_inner();
+    if entries.is_empty() {
+        return Err(BadRequest(Some(json!({ "error": "the playlist is empty" }))));
+    }
+    for entry in entries.iter() {
+        if let Err(e) = entry.validate() {
+            return Err(BadRequest(Some(json!({ "error": e.to_string() }))));
+        }
+    }
+    let names: Vec<String> = entries.iter().map(|entry| entry.name.clone()).collect();
+    renderer.send(Command::Playlist(entries));
+    Ok(json!({
+        "animations": names,
+        "current": 0,
+    }))
+}
+
+#[post("/playlist/next")]
+fn playlist_next(renderer: RenderState) -> JsonValue {
+    renderer.send(Command::LightBox(LightBoxCommand::Next));
+    json!({ "status": "ok" })
+}
+
+#[post("/playlist/previous")]
+fn playlist_previous(renderer: RenderState) -> JsonValue {
+    renderer.send(Command::LightBox(LightBoxCommand::Previous));
+    json!({ "status": "ok" })
+}
+
+#[post("/playlist/select/<index>")]
+fn playlist_select(index: usize, renderer: RenderState) -> Result<JsonValue, BadRequest<JsonValue>> {
+    if index >= renderer.status().playlist.len() {
+        return Err(BadRequest(Some(json!({ "error": "no animation at that index" }))));
+    }
+    renderer.send(Command::LightBox(LightBoxCommand::Select(index)));
+    Ok(json!({ "status": index }))
+}
+
+#[post("/playlist/select/<name>", rank = 2)]
+fn playlist_select_name(name: String, renderer: RenderState) -> Result<JsonValue, BadRequest<JsonValue>> {
+    if !renderer.status().playlist.contains(&name) {
+        return Err(BadRequest(Some(json!({ "error": "no animation with that name" }))));
+    }
+    renderer.send(Command::SelectName(name.clone()));
+    Ok(json!({ "status": name }))
+}
+
+#[post("/playlist/pause")]
+fn playlist_pause(renderer: RenderState) -> JsonValue {
+    renderer.send(Command::LightBox(LightBoxCommand::Pause));
+    json!({ "status": true })
+}
+
+#[post("/playlist/resume")]
+fn playlist_resume(renderer: RenderState) -> JsonValue {
+    renderer.send(Command::LightBox(LightBoxCommand::Resume));
+    json!({ "status": false })
+}
+
+#[get("/frame")]
+fn frame_status(renderer: RenderState) -> JsonValue {
+    let status = renderer.status();
+    json!({
+        "manual": status.manual.is_some(),
+        "remaining": status.manual,
+    })
+}
+
+#[put("/frame", format = "json", data = "<frame>")]
+fn set_frame(frame: Json<FrameRequest>, renderer: RenderState) -> Result<JsonValue, BadRequest<JsonValue>> {
+    let status = renderer.status();
+    let pixels = frame.decode(status.rows * status.cols)
+        .map_err(|e| BadRequest(Some(json!({ "error": e }))))?;
+    let timeout = frame::timeout(frame.timeout).map_err(|e| BadRequest(Some(json!({ "error": e }))))?;
+    renderer.send(Command::Frame { pixels, timeout });
+    Ok(json!({ "manual": true, "remaining": timeout }))
+}
+
+#[post("/frame/fill", format = "json", data = "<fill>")]
+fn fill_frame(fill: Json<FillRequest>, renderer: RenderState) -> Result<JsonValue, BadRequest<JsonValue>> {
+    let status = renderer.status();
+    let timeout = frame::timeout(fill.timeout).map_err(|e| BadRequest(Some(json!({ "error": e }))))?;
+    renderer.send(Command::Fill {
+        region: fill.region(status.rows, status.cols),
+        color: fill.color,
+        timeout,
+    });
+    Ok(json!({ "manual": true, "remaining": timeout }))
+}
+
+#[post("/frame/pixel", format = "json", data = "<pixel>")]
+fn set_pixel(pixel: Json<PixelRequest>, renderer: RenderState) -> Result<JsonValue, BadRequest<JsonValue>> {
+    let status = renderer.status();
+    if pixel.x >= status.cols || pixel.y >= status.rows {
+        return Err(BadRequest(Some(json!({ "error": "pixel is outside of the display" }))));
+    }
+    let timeout = frame::timeout(pixel.timeout).map_err(|e| BadRequest(Some(json!({ "error": e }))))?;
+    renderer.send(Command::Fill {
+        region: Region { x: pixel.x, y: pixel.y, width: 1, height: 1 },
+        color: pixel.color,
+        timeout,
+    });
+    Ok(json!({ "manual": true, "remaining": timeout }))
+}
+
+#[delete("/frame")]
+fn release_frame(renderer: RenderState) -> JsonValue {
+    renderer.send(Command::Release);
+    json!({ "manual": false })
+}
+
+#[get("/schedule")]
+fn schedule(renderer: RenderState) -> Json<Schedule> {
+    Json(Schedule::clone(&renderer.status().schedule))
+}
+
+#[put("/schedule", format = "json", data = "<schedule>")]
+fn set_schedule(schedule: Json<Schedule>, renderer: RenderState, config: State<Config>) -> Result<Json<Schedule>, BadRequest<JsonValue>> {
+    let schedule = schedule.into_inner();
+    schedule.validate().map_err(|e| BadRequest(Some(json!({ "error": e.to_string() }))))?;
+    if let Err(e) = save_schedule(&config.schedule, &schedule) {
+        println!("Couldn't save the schedule to {}: {}", config.schedule.display(), e);
+    }
+    renderer.send(Command::Schedule(schedule.clone()));
+    Ok(Json(schedule))
+}
+
+/// Mirrors the display: every rendered frame as a Server-Sent Event.
+#[get("/frames/stream")]
+fn frames_stream(broadcaster: State<FrameBroadcaster>) -> Result<Subscription, status::Custom<JsonValue>> {
+    broadcaster.subscribe().ok_or_else(|| {
+        let error = format!("no more than {} streams can be open at once", MAX_SUBSCRIBERS);
+        status::Custom(Status::ServiceUnavailable, json!({ "error": error }))
+    })
+}
+
+#[get("/frames/latest")]
+fn frames_latest(broadcaster: State<FrameBroadcaster>) -> Result<content::Json<String>, NotFound<JsonValue>> {
+    broadcaster.latest()
+        .map(content::Json)
+        .ok_or_else(|| NotFound(json!({ "error": "nothing has been rendered yet" })))
 }
 
 fn brightness_to_percentage(brightness: u8) -> u8 {
@@ -97,66 +270,115 @@ fn percentage_to_brightness(percentage: u8) -> u8 {
     (percentage as f64 * 255.0 / 100.0) as u8
 }
 
-struct MyLightBox(LightBox<GridDisplay>);
-
-impl Deref for MyLightBox {
-    type Target = LightBox<GridDisplay>;
-    fn deref(&self) -> &LightBox<GridDisplay> {
-        return &self.0;
-    }
+fn load_schedule(path: &Path) -> Schedule {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return Schedule::default(),
+    };
+    toml::from_str::<Schedule>(&text)
+        .map_err(|e| e.to_string())
+        .and_then(|schedule| schedule.validate().map(|_| schedule).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            println!("Ignoring {}: {}", path.display(), e);
+            Schedule::default()
+        })
 }
 
-impl DerefMut for MyLightBox {
-    fn deref_mut(&mut self) -> &mut LightBox<GridDisplay> {
-        return &mut self.0;
-    }
+fn save_schedule(path: &Path, schedule: &Schedule) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(path, toml::to_string(schedule)?)?;
+    Ok(())
 }
-unsafe impl Send for MyLightBox {}
 
-struct Manager {
-    pub lightbox: MyLightBox,
-    pub thread: Option<Thread>,
-    pub running: Arc<AtomicBool>,
+fn load_playlist(path: &Path) -> Result<Vec<PlaylistEntry>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    let entries: Vec<PlaylistEntry> = serde_json::from_str(&text)
+        .map_err(|e| format!("invalid playlist {}: {}", path.display(), e))?;
+    if entries.is_empty() {
+        return Err(format!("the playlist in {} is empty", path.display()));
+    }
+    for entry in entries.iter() {
+        entry.validate().map_err(|e| format!("invalid playlist {}: {}", path.display(), e))?;
+    }
+    Ok(entries)
 }
 
-//unsafe impl Send for Manager {}
+type Lights = LightBox<Broadcast<GridDisplay>>;
 
 fn main() {
+    let config = Config::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let playlist = match config.playlist {
+        Some(ref path) => Some(load_playlist(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        })),
+        None => None,
+    };
+
     let display = GridDisplayBuilder::new()
-        .gpio_pin(18)
+        .gpio_pin(config.pin)
+        .dma(config.dma)
+        .freq(config.freq)
+        .invert(config.invert)
         .brightness(50)
-        .strip_type(StripType::Ws2811Rgb)
-        .rows(7)
-        .cols(7)
-        .build();
-
-    let lightbox = LightBox::new(
-        display, vec![
-            // Box::new(CircleAnimation::default()),
-            // Box::new(StripeAnimation::default()),
-            Box::new(Fireflies::default()),
-            // Box::new(BasicAnimation::default()),
-        ],
-        30,
-    );
+        .strip_type(config.strip_type().unwrap())
+        .rows(config.rows)
+        .cols(config.cols)
+        .mapping(config.mapping)
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to set up the LEDs: {}", e);
+            process::exit(1);
+        });
+    let broadcaster = FrameBroadcaster::new();
+    let display = Broadcast::new(display, broadcaster.clone());
 
-    let manager = Manager {lightbox: MyLightBox(lightbox), thread: None, running: Arc::new(AtomicBool::new(false))};
+    // the playlist comes from the saved state
+    let lightbox = LightBox::new(display, Vec::new(), config.fps);
+
+    let renderer = RenderThread::spawn(
+        lightbox,
+        StateFile::new(config.state.clone()),
+        load_schedule(&config.schedule),
+    );
+    if let Some(entries) = playlist {
+        renderer.send(Command::Playlist(entries));
+    }
 
     let cors = CorsOptions::default()
         .allowed_origins(AllowedOrigins::all())
         .allowed_methods(
-            vec![Method::Get, Method::Post, Method::Patch]
+            vec![Method::Get, Method::Post, Method::Put, Method::Patch, Method::Delete]
                 .into_iter()
                 .map(From::from)
                 .collect(),
         )
         .allow_credentials(true);
 
+    let rocket_config = rocket::Config::build(Environment::active().unwrap_or(Environment::Development))
+        .address(config.address.clone())
+        .port(config.port)
+        .finalize()
+        .unwrap_or_else(|e| {
+            eprintln!("invalid address {:?}: {}", config.address, e);
+            process::exit(1);
+        });
+
     {
-        rocket::ignite()
+        rocket::custom(rocket_config)
             .attach(cors.to_cors().unwrap())
-            .mount("/", routes![index, power_status, set_power, brightness_status, set_brightness])
-            .manage(Arc::new(Mutex::new(manager)))
+            .mount("/", routes![
+                index, display_info, power_status, set_power, brightness_status, set_brightness,
+                playlist, playlist_current, set_playlist, playlist_next, playlist_previous,
+                playlist_select, playlist_select_name, playlist_pause, playlist_resume,
+                frame_status, set_frame, fill_frame, set_pixel, release_frame,
+                schedule, set_schedule, frames_stream, frames_latest,
+            ])
+            .manage(renderer)
+            .manage(broadcaster)
+            .manage(config)
             .launch();
     }
 
@@ -0,0 +1,493 @@
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use lightbox::Command as LightBoxCommand;
+use lightbox::playlist::{build_playlist, PlaylistEntry, PlaylistError};
+use lightbox::schedule::{Schedule, Scheduler, Settings};
+use rpi_ws281x_display::{Error as DisplayError, Pixel, PixelDisplay};
+
+use super::{percentage_to_brightness, Lights};
+use frame::MAX_TIMEOUT;
+use state::{SavedState, StateFile};
+
+/// How often the schedule is checked while the lights are off.
+const SCHEDULE_TICK: Duration = Duration::from_secs(1);
+
+/// A rectangle of the display, in LEDs.
+#[derive(Clone, Copy, Debug)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Everything the web handlers are able to ask of the render thread.
+pub enum Command {
+    Power(bool),
+    /// Moves around the playlist, pauses it or sets the brightness, see
+    /// `LightBox::handle`.
+    LightBox(LightBoxCommand),
+    SelectName(String),
+    Playlist(Vec<PlaylistEntry>),
+    /// Shows a whole frame, given in row-major order, for `timeout`
+    /// seconds before going back to the playlist.
+    Frame { pixels: Vec<Pixel>, timeout: f64 },
+    /// Fills part of the display for `timeout` seconds before going back
+    /// to the playlist.
+    Fill { region: Region, color: Pixel, timeout: f64 },
+    /// Leaves manual mode right away.
+    Release,
+    Schedule(Schedule),
+}
+
+/// A snapshot of the render thread's state, published after every frame
+/// so that reading it never waits on rendering.
+#[derive(Clone, Debug, Default)]
+pub struct Status {
+    pub running: bool,
+    pub brightness: u8,
+    pub rows: usize,
+    pub cols: usize,
+    pub playlist: Vec<String>,
+    pub animation: usize,
+    pub elapsed: f64,
+    pub paused: bool,
+    /// Seconds left before externally pushed frames give way to the
+    /// playlist again, if any are being shown.
+    pub manual: Option<f64>,
+    pub schedule: Arc<Schedule>,
+    /// Why the last frame failed to render, if it did.
+    pub error: Option<String>,
+}
+
+/// The single long-lived thread that owns the `LightBox`. It renders
+/// while the power is on and otherwise sleeps until the next command.
+pub struct RenderThread {
+    commands: Mutex<Sender<Command>>,
+    status: Arc<RwLock<Status>>,
+    _handle: JoinHandle<()>,
+}
+
+impl RenderThread {
+    /// Starts rendering with the settings saved in `state_file`, saving
+    /// them back whenever they change. Restored settings stay until the
+    /// next schedule rule fires.
+    pub fn spawn(lightbox: Lights, state_file: StateFile, schedule: Schedule) -> Self {
+        let (tx, rx) = channel();
+        let renderer = Renderer::new(lightbox, state_file, schedule);
+        let status = Arc::new(RwLock::new(renderer.status()));
+        let status_t = status.clone();
+        let handle = thread::spawn(move || renderer.run(rx, status_t));
+        RenderThread {
+            commands: Mutex::new(tx),
+            status,
+            _handle: handle,
+        }
+    }
+
+    pub fn send(&self, command: Command) {
+        // the render thread only goes away when the process does
+        let _ = self.commands.lock().unwrap().send(command);
+    }
+
+    pub fn status(&self) -> Status {
+        self.status.read().unwrap().clone()
+    }
+}
+
+/// Set while frames pushed over HTTP are on the display.
+struct Manual {
+    until: Instant,
+    /// Whether the playlist was paused before manual mode paused it.
+    was_paused: bool,
+}
+
+struct Renderer {
+    lightbox: Lights,
+    running: bool,
+    manual: Option<Manual>,
+    scheduler: Scheduler,
+    /// The entries the current playlist was built from.
+    playlist: Vec<PlaylistEntry>,
+    state_file: StateFile,
+    saved: SavedState,
+    error: Option<String>,
+}
+
+impl Renderer {
+    fn new(lightbox: Lights, state_file: StateFile, schedule: Schedule) -> Self {
+        let mut scheduler = Scheduler::new(schedule);
+        // without saved settings, start with whatever the schedule says
+        let saved = match state_file.load() {
+            Some(saved) => {
+                scheduler.skip_to_now();
+                saved
+            }
+            None => SavedState::default(),
+        };
+        let mut renderer = Renderer {
+            lightbox,
+            running: false,
+            manual: None,
+            scheduler,
+            playlist: Vec::new(),
+            state_file,
+            saved: saved.clone(),
+            error: None,
+        };
+        renderer.restore(saved);
+        renderer
+    }
+
+    fn run(mut self, commands: Receiver<Command>, status: Arc<RwLock<Status>>) {
+        loop {
+            // while the lights are off there is nothing to do but wait,
+            // at most until manual mode is over or the schedule is due
+            if !self.running {
+                let timeout = match self.manual {
+                    Some(ref manual) => manual.until.saturating_duration_since(Instant::now()).min(SCHEDULE_TICK),
+                    None => SCHEDULE_TICK,
+                };
+                let received = commands.recv_timeout(timeout);
+                match received {
+                    Ok(command) => self.apply(command),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            loop {
+                match commands.try_recv() {
+                    Ok(command) => self.apply(command),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            }
+
+            if let Some(settings) = self.scheduler.poll() {
+                self.apply_settings(settings);
+            }
+
+            let expired = match self.manual {
+                Some(ref manual) => manual.until <= Instant::now(),
+                None => false,
+            };
+            if expired {
+                self.release();
+            }
+
+            if self.running {
+                let result = self.lightbox.update();
+                self.report(result);
+            }
+
+            *status.write().unwrap() = self.status();
+        }
+    }
+
+    fn status(&self) -> Status {
+        let lightbox = &self.lightbox;
+        let now = Instant::now();
+        Status {
+            running: self.running,
+            brightness: lightbox.display.get_brightness(),
+            rows: lightbox.display.rows(),
+            cols: lightbox.display.cols(),
+            playlist: lightbox.playlist_names().iter().map(|name| name.to_string()).collect(),
+            animation: lightbox.current_index(),
+            elapsed: lightbox.elapsed(),
+            paused: match self.manual {
+                Some(ref manual) => manual.was_paused,
+                None => lightbox.is_paused(),
+            },
+            manual: self.manual.as_ref().map(|m| m.until.saturating_duration_since(now).as_secs_f64()),
+            schedule: Arc::clone(self.scheduler.schedule()),
+            error: self.error.clone(),
+        }
+    }
+
+    fn apply(&mut self, command: Command) {
+        match command {
+            Command::Power(on) => {
+                self.set_power(on);
+                self.save_state();
+            }
+            Command::LightBox(command) => self.handle(command),
+            Command::SelectName(name) => {
+                self.lightbox.select_by_name(&name);
+            }
+            Command::Playlist(entries) => {
+                if let Err(e) = self.set_playlist(&entries) {
+                    println!("Ignoring playlist: {}", e);
+                }
+                self.save_state();
+            }
+            Command::Frame { pixels, timeout } => {
+                self.enter_manual(timeout);
+                let display = &mut self.lightbox.display;
+                display.set_frame(&pixels);
+                let result = display.render();
+                self.report(result);
+            }
+            Command::Fill { region, color, timeout } => {
+                self.enter_manual(timeout);
+                let display = &mut self.lightbox.display;
+                for row in display.pixels_mut().skip(region.y).take(region.height) {
+                    for pixel in row.iter_mut().skip(region.x).take(region.width) {
+                        *pixel = color;
+                    }
+                }
+                let result = display.render();
+                self.report(result);
+            }
+            Command::Release => self.release(),
+            Command::Schedule(schedule) => self.scheduler.set_schedule(schedule),
+        }
+    }
+
+    fn handle(&mut self, command: LightBoxCommand) {
+        // manual mode keeps the playlist paused, so remember the request
+        // for when it's over instead
+        match (command, self.manual.as_mut()) {
+            (LightBoxCommand::Pause, Some(manual)) | (LightBoxCommand::Step, Some(manual)) => {
+                manual.was_paused = true;
+            }
+            (LightBoxCommand::Resume, Some(manual)) => manual.was_paused = false,
+            (LightBoxCommand::TogglePause, Some(manual)) => manual.was_paused = !manual.was_paused,
+            _ => {
+                if let Err(e) = self.lightbox.handle(command) {
+                    self.report(Err(e));
+                }
+            }
+        }
+        if let LightBoxCommand::SetBrightness(_) | LightBoxCommand::AdjustBrightness(_) = command {
+            self.save_state();
+        }
+    }
+
+    fn set_power(&mut self, on: bool) {
+        if on && !self.running {
+            self.lightbox.reset();
+        } else if !on && self.running {
+            let result = self.lightbox.clear();
+            self.report(result);
+        }
+        self.running = on;
+    }
+
+    fn set_playlist(&mut self, entries: &[PlaylistEntry]) -> Result<(), PlaylistError> {
+        let playlist = build_playlist(entries)?;
+        self.lightbox.set_playlist(playlist);
+        self.playlist = entries.to_vec();
+        Ok(())
+    }
+
+    fn restore(&mut self, state: SavedState) {
+        self.lightbox.display.set_brightness(state.brightness);
+        if let Err(e) = self.set_playlist(&state.playlist) {
+            println!("Ignoring saved playlist: {}", e);
+            self.set_playlist(&SavedState::default().playlist).unwrap();
+        }
+        self.set_power(state.power);
+        self.save_state();
+    }
+
+    /// Writes the settings that survive a restart to the state file, if
+    /// they changed since the last time.
+    fn save_state(&mut self) {
+        let state = SavedState {
+            power: self.running,
+            brightness: self.lightbox.display.get_brightness(),
+            playlist: self.playlist.clone(),
+        };
+        if state != self.saved {
+            if let Err(e) = self.state_file.save(&state) {
+                println!("Couldn't save settings: {}", e);
+            }
+            self.saved = state;
+        }
+    }
+
+    /// Applies whatever a schedule rule changes.
+    fn apply_settings(&mut self, settings: Settings) {
+        if let Some(brightness) = settings.brightness {
+            self.lightbox.display.set_brightness(percentage_to_brightness(brightness));
+        }
+        if let Some(ref entries) = settings.playlist {
+            if let Err(e) = self.set_playlist(entries) {
+                println!("Ignoring scheduled playlist: {}", e);
+            }
+        }
+        if let Some(on) = settings.power {
+            self.set_power(on);
+        }
+        self.save_state();
+    }
+
+    /// Keeps track of whether rendering works, logging when that changes
+    /// rather than on every failed frame. Failed frames are simply tried
+    /// again on the next one.
+    fn report(&mut self, result: Result<(), DisplayError>) {
+        match result {
+            Ok(()) => {
+                if self.error.take().is_some() {
+                    println!("Rendering works again");
+                }
+            }
+            Err(e) => {
+                let message = e.to_string();
+                if self.error.as_ref() != Some(&message) {
+                    println!("Failed to render: {}", message);
+                }
+                self.error = Some(message);
+            }
+        }
+    }
+
+    /// Pauses the playlist so that it leaves pushed pixels alone, or
+    /// extends the timeout if it already is. Timeouts are at most
+    /// `MAX_TIMEOUT`.
+    fn enter_manual(&mut self, timeout: f64) {
+        let now = Instant::now();
+        let until = Duration::try_from_secs_f64(timeout.clamp(0.0, MAX_TIMEOUT))
+            .ok()
+            .and_then(|timeout| now.checked_add(timeout))
+            .unwrap_or(now);
+        match self.manual {
+            Some(ref mut manual) => manual.until = until,
+            None => {
+                self.manual = Some(Manual { until, was_paused: self.lightbox.is_paused() });
+                self.lightbox.pause();
+            }
+        }
+    }
+
+    fn release(&mut self) {
+        if let Some(manual) = self.manual.take() {
+            if !manual.was_paused {
+                self.lightbox.resume();
+            }
+            if !self.running {
+                let result = self.lightbox.clear();
+                self.report(result);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::{Path, PathBuf};
+
+    use lightbox::LightBox;
+    use rs_ws281x::StripType;
+
+    use display::GridDisplayBuilder;
+    use stream::{Broadcast, FrameBroadcaster};
+
+    /// A state file of the test's own, removed again when it's done.
+    struct TempState(PathBuf);
+
+    impl TempState {
+        fn new(name: &str) -> Self {
+            TempState(env::temp_dir().join(format!("neopixels-{}-{}.json", name, ::std::process::id())))
+        }
+    }
+
+    impl Drop for TempState {
+        fn drop(&mut self) {
+            let _ = ::std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn renderer(state: &Path, schedule: Schedule) -> Renderer {
+        let display = GridDisplayBuilder::new()
+            .rows(2)
+            .cols(2)
+            .gpio_pin(18)
+            .brightness(255)
+            .strip_type(StripType::Ws2811Grb)
+            .build()
+            .unwrap();
+        let lightbox = LightBox::new(Broadcast::new(display, FrameBroadcaster::new()), Vec::new(), 30);
+        Renderer::new(lightbox, StateFile::new(state), schedule)
+    }
+
+    #[test]
+    fn test_manual_mode_from_hostile_requests() {
+        let state = TempState::new("manual");
+        let mut renderer = renderer(&state.0, Schedule::default());
+        let region = Region { x: 1, y: 0, width: usize::MAX, height: usize::MAX };
+        renderer.apply(Command::Fill { region, color: Pixel::rgb(255, 0, 0), timeout: 1e300 });
+        assert_eq!(renderer.lightbox.display.frame(), &[
+            Pixel::default(), Pixel::rgb(255, 0, 0),
+            Pixel::default(), Pixel::rgb(255, 0, 0),
+        ]);
+        let remaining = renderer.status().manual.unwrap();
+        assert!(remaining > MAX_TIMEOUT - 1.0 && remaining <= MAX_TIMEOUT);
+    }
+
+    #[test]
+    fn test_playlist_controls() {
+        let state = TempState::new("controls");
+        let mut renderer = renderer(&state.0, Schedule::default());
+        renderer.apply(Command::LightBox(LightBoxCommand::SetBrightness(20)));
+        assert_eq!(StateFile::new(state.0.clone()).load().unwrap().brightness, 20);
+
+        // pausing while pushed pixels show waits until they're gone
+        let region = Region { x: 0, y: 0, width: 1, height: 1 };
+        renderer.apply(Command::Fill { region, color: Pixel::rgb(1, 2, 3), timeout: 1.0 });
+        renderer.apply(Command::LightBox(LightBoxCommand::Pause));
+        assert!(renderer.status().paused);
+        renderer.apply(Command::Release);
+        assert!(renderer.lightbox.is_paused());
+        renderer.apply(Command::LightBox(LightBoxCommand::Resume));
+        assert!(!renderer.lightbox.is_paused());
+    }
+
+    #[test]
+    fn test_pushed_frames_reach_the_strip() {
+        let state = TempState::new("strip");
+        let mut renderer = renderer(&state.0, Schedule::default());
+        let white = Pixel::rgb(255, 255, 255);
+        let pixels = vec![Pixel::rgb(255, 0, 0), Pixel::rgb(0, 255, 0), Pixel::rgb(0, 0, 255), white];
+        renderer.apply(Command::Frame { pixels, timeout: 1.0 });
+
+        // serpentine GRB at the default brightness of 50, so the second
+        // row runs backwards
+        let device = renderer.lightbox.display.get_ref().controller().device();
+        assert_eq!(device.renders(), 1);
+        assert_eq!(device.wire(0), &[0, 50, 0, 50, 0, 0, 50, 50, 50, 0, 0, 50]);
+    }
+
+    #[test]
+    fn test_schedule_leaves_restored_state_alone() {
+        let state = TempState::new("restore");
+        let path = &state.0;
+        let saved = SavedState {
+            power: true,
+            brightness: 200,
+            playlist: vec![PlaylistEntry::new("circle", None)],
+        };
+        StateFile::new(path.clone()).save(&saved).unwrap();
+        let schedule: Schedule = ::toml::from_str("[[rules]]\nat = \"00:00\"\npower = false\nbrightness = 10\n").unwrap();
+
+        let mut restored = renderer(path, schedule.clone());
+        assert_eq!(restored.scheduler.poll(), None);
+        let status = restored.status();
+        assert!(status.running);
+        assert_eq!(status.brightness, 200);
+        assert_eq!(status.playlist, vec!["circle"]);
+        ::std::fs::remove_file(path).unwrap();
+
+        // with nothing to restore the schedule decides
+        let mut fresh = renderer(path, schedule);
+        let settings = fresh.scheduler.poll().unwrap();
+        assert_eq!(settings.power, Some(false));
+        assert_eq!(settings.brightness, Some(10));
+    }
+}
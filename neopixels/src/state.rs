@@ -0,0 +1,80 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use lightbox::playlist::PlaylistEntry;
+use serde_json;
+
+/// The settings that survive a restart.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedState {
+    pub power: bool,
+    pub brightness: u8,
+    pub playlist: Vec<PlaylistEntry>,
+}
+
+impl Default for SavedState {
+    fn default() -> Self {
+        SavedState {
+            power: false,
+            brightness: 50,
+            playlist: vec![PlaylistEntry::new("fireflies", None)],
+        }
+    }
+}
+
+/// A JSON file holding the `SavedState`.
+pub struct StateFile {
+    path: PathBuf,
+}
+
+impl StateFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        StateFile { path: path.into() }
+    }
+
+    /// Reads the saved state, if there is any yet and it can be read.
+    pub fn load(&self) -> Option<SavedState> {
+        let file = File::open(&self.path).ok()?;
+        serde_json::from_reader(file)
+            .map_err(|e| println!("Ignoring {}: {}", self.path.display(), e))
+            .ok()
+    }
+
+    /// Writes to a temporary file first and renames it over the old one,
+    /// so that losing power halfway through never leaves a broken file.
+    pub fn save(&self, state: &SavedState) -> io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        {
+            let mut file = File::create(&tmp)?;
+            serde_json::to_writer_pretty(&mut file, state)?;
+            file.write_all(b"\n")?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_round_trip() {
+        let path = env::temp_dir().join(format!("neopixels-state-{}.json", ::std::process::id()));
+        let file = StateFile::new(path.clone());
+        assert_eq!(file.load(), None);
+
+        let state = SavedState {
+            power: true,
+            brightness: 128,
+            playlist: vec![PlaylistEntry::new("circle", Some(10.0))],
+        };
+        file.save(&state).unwrap();
+        assert_eq!(file.load(), Some(state));
+        fs::remove_file(path).unwrap();
+    }
+}
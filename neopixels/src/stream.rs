@@ -0,0 +1,336 @@
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::time::Duration;
+
+use rocket::Request;
+use rocket::http::ContentType;
+use rocket::response::{self, Responder, Response};
+use serde_json;
+
+use rpi_ws281x_display::{Pixel, PixelDisplay, Result};
+
+/// How many frames a slow subscriber may fall behind before frames are
+/// dropped for it.
+const BACKLOG: usize = 2;
+
+/// How long a stream goes without a frame, e.g. while the lights are off,
+/// before it sends a comment instead. Writing it is what finds out that a
+/// client has gone away, which frees up its worker thread.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// How many streams may be open at once. Each one holds on to a Rocket
+/// worker thread, so this has to stay well below `workers` or a few
+/// browser tabs would leave none for the rest of the API.
+pub const MAX_SUBSCRIBERS: usize = 4;
+
+#[derive(Default, Serialize)]
+struct FrameEvent {
+    rows: usize,
+    cols: usize,
+    brightness: u8,
+    pixels: Vec<Pixel>,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    senders: Vec<SyncSender<Arc<String>>>,
+    /// Kept as it is and only turned into JSON when someone asks for it,
+    /// since most of the time nobody is watching.
+    latest: Option<FrameEvent>,
+}
+
+/// Hands every rendered frame, serialized as JSON, to whoever is
+/// listening. Cloning it shares the same set of subscribers.
+#[derive(Clone, Default)]
+pub struct FrameBroadcaster {
+    subscribers: Arc<Mutex<Subscribers>>,
+    /// Shared with every open subscription, so that its count says how
+    /// many there are.
+    open: Arc<()>,
+}
+
+impl FrameBroadcaster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts listening, beginning with the last frame published, or
+    /// `None` if `MAX_SUBSCRIBERS` streams are already open.
+    pub fn subscribe(&self) -> Option<Subscription> {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if Arc::strong_count(&self.open) > MAX_SUBSCRIBERS {
+            return None;
+        }
+        let (tx, rx) = sync_channel(BACKLOG);
+        if let Some(ref latest) = subscribers.latest {
+            let _ = tx.try_send(Arc::new(serde_json::to_string(latest).unwrap()));
+        }
+        subscribers.senders.push(tx);
+        let mut subscription = Subscription::new(rx, KEEP_ALIVE);
+        subscription.open = self.open.clone();
+        Some(subscription)
+    }
+
+    /// The last frame published, as JSON.
+    pub fn latest(&self) -> Option<String> {
+        self.subscribers.lock().unwrap().latest.as_ref().map(|frame| serde_json::to_string(frame).unwrap())
+    }
+
+    fn publish<D: PixelDisplay + ?Sized>(&self, display: &D) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        {
+            let latest = subscribers.latest.get_or_insert_with(FrameEvent::default);
+            latest.rows = display.rows();
+            latest.cols = display.cols();
+            latest.brightness = display.get_brightness();
+            latest.pixels.clear();
+            latest.pixels.extend_from_slice(display.frame());
+        }
+        if subscribers.senders.is_empty() {
+            return;
+        }
+
+        let frame = Arc::new(serde_json::to_string(&subscribers.latest).unwrap());
+        // a full queue means the client is slow, so it misses this frame;
+        // a disconnected one means it has gone away
+        subscribers.senders.retain(|tx| match tx.try_send(frame.clone()) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+/// Wraps a display and publishes a copy of every frame it renders.
+pub struct Broadcast<D: PixelDisplay> {
+    display: D,
+    broadcaster: FrameBroadcaster,
+}
+
+impl<D: PixelDisplay> Broadcast<D> {
+    pub fn new(display: D, broadcaster: FrameBroadcaster) -> Self {
+        Broadcast { display, broadcaster }
+    }
+
+    pub fn get_ref(&self) -> &D {
+        &self.display
+    }
+}
+
+impl<D: PixelDisplay> PixelDisplay for Broadcast<D> {
+    fn rows(&self) -> usize {
+        self.display.rows()
+    }
+
+    fn cols(&self) -> usize {
+        self.display.cols()
+    }
+
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
+        self.display.set_at(x, y, pixel)
+    }
+
+    fn get_at(&self, x: usize, y: usize) -> Pixel {
+        self.display.get_at(x, y)
+    }
+
+    fn frame(&self) -> &[Pixel] {
+        self.display.frame()
+    }
+
+    fn frame_mut(&mut self) -> &mut [Pixel] {
+        self.display.frame_mut()
+    }
+
+    fn render(&mut self) -> Result<()> {
+        self.display.render()?;
+        self.broadcaster.publish(&self.display);
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.display.clear()
+    }
+
+    fn get_brightness(&self) -> u8 {
+        self.display.get_brightness()
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        self.display.set_brightness(brightness)
+    }
+
+    fn fill(&mut self, pixel: Pixel) {
+        self.display.fill(pixel)
+    }
+
+    fn set_frame(&mut self, frame: &[Pixel]) {
+        self.display.set_frame(frame)
+    }
+}
+
+/// A stream of frames as Server-Sent Events, one `frame` event per
+/// render. Each open stream holds on to one of Rocket's worker threads
+/// until the client goes away.
+pub struct Subscription {
+    frames: Receiver<Arc<String>>,
+    keep_alive: Duration,
+    pending: Vec<u8>,
+    pos: usize,
+    flushed: bool,
+    /// Whether the last read filled the caller's buffer.
+    filled: bool,
+    open: Arc<()>,
+}
+
+impl Subscription {
+    fn new(frames: Receiver<Arc<String>>, keep_alive: Duration) -> Self {
+        Subscription {
+            frames,
+            keep_alive,
+            // sent straight away so that the client knows it's connected
+            pending: b": connected\n\n".to_vec(),
+            pos: 0,
+            flushed: false,
+            filled: false,
+            open: Arc::new(()),
+        }
+    }
+}
+
+impl Read for Subscription {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.pending.len() {
+            // Rocket buffers the body until a read would block, so say
+            // so once after every event to get it onto the wire. It takes
+            // a would-block into an empty buffer for the end of the body
+            // though, so if the event ended a buffer, pad the next one
+            // with an empty comment first.
+            if !self.flushed {
+                if self.filled {
+                    self.pending = b":\n".to_vec();
+                    self.pos = 0;
+                } else {
+                    self.flushed = true;
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+            } else {
+                self.pending = match self.frames.recv_timeout(self.keep_alive) {
+                    Ok(frame) => format!("event: frame\ndata: {}\n\n", frame).into_bytes(),
+                    Err(RecvTimeoutError::Timeout) => b": keep-alive\n\n".to_vec(),
+                    Err(RecvTimeoutError::Disconnected) => return Ok(0),
+                };
+                self.pos = 0;
+                self.flushed = false;
+            }
+        }
+        let len = buf.len().min(self.pending.len() - self.pos);
+        buf[..len].copy_from_slice(&self.pending[self.pos..self.pos + len]);
+        self.pos += len;
+        self.filled = len == buf.len();
+        Ok(len)
+    }
+}
+
+impl<'r> Responder<'r> for Subscription {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        Response::build()
+            .header(ContentType::new("text", "event-stream"))
+            .raw_header("Cache-Control", "no-cache")
+            .streamed_body(self)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpi_ws281x_display::MemoryDisplay;
+
+    /// Reads the body the way Rocket does: a chunk at a time, flushing
+    /// when a read would block and stopping at an empty chunk. Returns
+    /// what was flushed once `done` is happy with it.
+    fn stream(subscription: &mut Subscription, chunk: usize, done: &dyn Fn(&str) -> bool) -> String {
+        let mut flushed = String::new();
+        let mut unflushed = String::new();
+        let mut buf = vec![0u8; chunk];
+        loop {
+            let mut len = 0;
+            let mut flush = false;
+            while len < chunk {
+                match subscription.read(&mut buf[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        flush = true;
+                        break;
+                    }
+                    Err(e) => panic!("{}", e),
+                }
+            }
+            if len == 0 {
+                return flushed;
+            }
+            unflushed.push_str(::std::str::from_utf8(&buf[..len]).unwrap());
+            if flush {
+                flushed.push_str(&unflushed);
+                unflushed.clear();
+                if done(&flushed) {
+                    return flushed;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_subscribers_receive_frames() {
+        let broadcaster = FrameBroadcaster::new();
+        let mut display = Broadcast::new(MemoryDisplay::new(2, 1), broadcaster.clone());
+        let mut subscription = broadcaster.subscribe().unwrap();
+
+        display.set_at(1, 0, Pixel::rgb(255, 0, 0));
+        display.render().unwrap();
+        drop(display);
+
+        let frame = r##"{"rows":1,"cols":2,"brightness":255,"pixels":["#000000","#ff0000"]}"##;
+        let events = stream(&mut subscription, 4096, &|events| events.ends_with("}\n\n"));
+        assert_eq!(events, format!(": connected\n\nevent: frame\ndata: {}\n\n", frame));
+        assert_eq!(broadcaster.latest(), Some(frame.to_string()));
+    }
+
+    #[test]
+    fn test_events_ending_a_chunk() {
+        let (tx, rx) = sync_channel(BACKLOG);
+        let mut subscription = Subscription::new(rx, KEEP_ALIVE);
+        tx.send(Arc::new("{}".to_string())).unwrap();
+        drop(tx);
+
+        // ": connected\n\n" fills the first chunk exactly
+        let events = stream(&mut subscription, 13, &|events| events.ends_with("}\n\n"));
+        assert_eq!(events, ": connected\n\n:\nevent: frame\ndata: {}\n\n");
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let broadcaster = FrameBroadcaster::new();
+        let mut display = Broadcast::new(MemoryDisplay::new(1, 1), broadcaster.clone());
+        display.render().unwrap();
+        assert_eq!(broadcaster.latest(), Some(r##"{"rows":1,"cols":1,"brightness":255,"pixels":["#000000"]}"##.to_string()));
+
+        let (tx, rx) = sync_channel(BACKLOG);
+        let mut subscription = Subscription::new(rx, Duration::from_millis(10));
+        let events = stream(&mut subscription, 4096, &|events| events.ends_with(": keep-alive\n\n"));
+        assert_eq!(events, ": connected\n\n: keep-alive\n\n");
+        drop(tx);
+        assert_eq!(stream(&mut subscription, 4096, &|_| false), "");
+    }
+
+    #[test]
+    fn test_max_subscribers() {
+        let broadcaster = FrameBroadcaster::new();
+        let mut subscriptions: Vec<_> = (0..MAX_SUBSCRIBERS).map(|_| broadcaster.subscribe().unwrap()).collect();
+        assert!(broadcaster.subscribe().is_none());
+        subscriptions.pop();
+        assert!(broadcaster.subscribe().is_some());
+    }
+}
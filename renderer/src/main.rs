@@ -0,0 +1,148 @@
+//! Renders a playlist without any LEDs or windows, e.g.
+//!
+//! ```text
+//! renderer --fps 30 --output preview.gif fireflies:10 circle:5
+//! renderer --duration 60 --format raw --output - stripe | ...
+//! ```
+extern crate clap;
+extern crate image;
+extern crate lightbox;
+extern crate rpi_ws281x_display;
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process;
+
+use clap::{App, Arg};
+use lightbox::{LightBox, VirtualClock};
+use lightbox::cli::{self, parse, Options};
+use lightbox::playlist::build_playlist;
+use rpi_ws281x_display::MemoryDisplay;
+use rpi_ws281x_display::recorder::{DotStyle, RecordFormat, Recorder, RecorderOptions};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// One PNG per frame written into a directory.
+    Png,
+    Gif,
+    Apng,
+    /// Every frame as rows * cols RGBW bytes, back to back.
+    Raw,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "png" => Some(Format::Png),
+            "gif" => Some(Format::Gif),
+            "apng" => Some(Format::Apng),
+            "raw" => Some(Format::Raw),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &str) -> Format {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("gif") => Format::Gif,
+            Some("png") | Some("apng") => Format::Apng,
+            Some("raw") | Some("bin") => Format::Raw,
+            _ if path == "-" => Format::Raw,
+            _ => Format::Png,
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let playlist_help = cli::playlist_help();
+    let matches = App::new("renderer")
+        .about("Renders a lightbox playlist to image files without LEDs or a window")
+        .arg(cli::playlist_arg(&playlist_help).required(true))
+        .arg(Arg::with_name("output").short("o").long("output").takes_value(true).required(true)
+            .help("File to write, a directory for png frames, or - for raw frames on stdout"))
+        .arg(Arg::with_name("format").short("f").long("format").takes_value(true)
+            .possible_values(&["png", "gif", "apng", "raw"])
+            .help("Output format, guessed from --output when omitted"))
+        .arg(Arg::with_name("duration").short("d").long("duration").takes_value(true)
+            .help("Seconds to render, defaults to the length of the playlist"))
+        .args(&cli::display_args())
+        .arg(Arg::with_name("dot-size").long("dot-size").takes_value(true).default_value("16")
+            .help("Size in image pixels of a single LED"))
+        .arg(Arg::with_name("spacing").long("spacing").takes_value(true).default_value("4")
+            .help("Gap in image pixels between LEDs"))
+        .arg(Arg::with_name("style").long("style").takes_value(true).default_value("glow")
+            .possible_values(&["square", "circle", "glow"]))
+        .get_matches();
+
+    let Options { playlist: entries, fps, cols, rows } = Options::from_matches(&matches)?;
+    let output = matches.value_of("output").unwrap();
+    let format = match matches.value_of("format") {
+        Some(name) => Format::from_name(name).unwrap(),
+        None => Format::from_path(output),
+    };
+    let duration: f64 = match matches.value_of("duration") {
+        Some(_) => parse(&matches, "duration")?,
+        None => entries.iter()
+            .map(|entry| entry.duration)
+            .sum::<Option<f64>>()
+            .ok_or("--duration is required unless every animation has a duration")?,
+    };
+    let options = RecorderOptions {
+        dot_size: parse(&matches, "dot-size")?,
+        spacing: parse(&matches, "spacing")?,
+        style: match matches.value_of("style").unwrap() {
+            "square" => DotStyle::Square,
+            "circle" => DotStyle::Circle,
+            _ => DotStyle::Glow,
+        },
+        fps,
+    };
+
+    let display = MemoryDisplay::new(cols, rows);
+    let playlist = build_playlist(&entries).map_err(|e| e.to_string())?;
+    let recorder = Recorder::new(display, options);
+    let mut lightbox = LightBox::with_clock(recorder, playlist, fps as u64, VirtualClock::new());
+
+    let frames = (duration * fps as f64).round() as usize;
+    for _ in 0..frames {
+        lightbox.update().map_err(|e| e.to_string())?;
+    }
+
+    write_frames(&lightbox.display, format, output)
+        .map_err(|e| format!("failed to write {}: {}", output, e))
+}
+
+fn write_frames(recorder: &Recorder<MemoryDisplay>, format: Format, output: &str) -> io::Result<()> {
+    match format {
+        Format::Png => {
+            fs::create_dir_all(output)?;
+            for index in 0..recorder.frames().len() {
+                let path = Path::new(output).join(format!("frame_{:05}.png", index));
+                recorder.render_frame(index).save(path)?;
+            }
+            Ok(())
+        }
+        Format::Gif => recorder.encode(BufWriter::new(File::create(output)?), RecordFormat::Gif),
+        Format::Apng => recorder.encode(BufWriter::new(File::create(output)?), RecordFormat::Apng),
+        Format::Raw => {
+            let mut w: Box<dyn Write> = if output == "-" {
+                Box::new(BufWriter::new(io::stdout()))
+            } else {
+                Box::new(BufWriter::new(File::create(output)?))
+            };
+            for frame in recorder.frames() {
+                for pixel in frame {
+                    w.write_all(&[pixel.r(), pixel.g(), pixel.b(), pixel.w()])?;
+                }
+            }
+            w.flush()
+        }
+    }
+}
+
+fn main() {
+    if let Err(message) = run() {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
@@ -24,13 +24,13 @@ impl Ball {
             id,
             pos_x: rng.gen_range(0, cols),
             pos_y: rng.gen_range(0, rows),
-            color: Pixel(rng.gen()),
+            color: Pixel::from(rng.gen::<u32>()),
             elapsed: 0.0,
             ttl: rng.gen_range(1.0, 60.0),
         }
     }
 
-    pub fn update<T: PixelDisplay>(&mut self, display: &mut T, delta: f64) {
+    pub fn update<T: PixelDisplay + ?Sized>(&mut self, display: &mut T, delta: f64) {
         self.elapsed += delta;
         let ratio = (self.elapsed / self.ttl).min(1.0);
 
@@ -59,7 +59,7 @@ pub struct Fireflies {
     balls: Vec<Ball>,
 }
 
-impl<T: PixelDisplay> Animation<T> for Fireflies {
+impl<T: PixelDisplay + ?Sized> Animation<T> for Fireflies {
     fn setup(&mut self, _display: &mut T) {
         self.balls = Vec::new();
         self.ball_id = 0;
@@ -73,11 +73,8 @@ impl<T: PixelDisplay> Animation<T> for Fireflies {
         }
 
         // fade out all of the pixels to imitate trails
-        for row in 0..display.rows() {
-            for col in 0..display.cols() {
-                let p = display.get_at(col, row);
-                display.set_at(col, row, p.scale(0.90));
-            }
+        for p in display.frame_mut() {
+            *p = p.scale(0.90);
         }
 
         for ball in self.balls.iter_mut() {
@@ -91,4 +88,8 @@ impl<T: PixelDisplay> Animation<T> for Fireflies {
 //        elapsed > 30.0
         false
     }
+
+    fn name(&self) -> &str {
+        "fireflies"
+    }
 }
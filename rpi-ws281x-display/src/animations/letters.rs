@@ -1,10 +1,8 @@
-use image::{Rgb, RgbImage};
+use image::Rgba;
 use imageproc::drawing::draw_text_mut;
-use rusttype::{FontCollection, Font, Scale};
-use std::env;
-use std::path::Path;
+use rusttype::{FontCollection, Scale};
 
-use ::{PixelDisplay, Animation};
+use ::{AlphaMode, BlitOptions, PixelDisplay, Animation, Raster};
 use Pixel;
 
 static ALPHA: [char; 26] = [
@@ -21,28 +19,29 @@ pub struct Letters {
     color: Pixel,
 }
 
-impl<T: PixelDisplay> Animation<T> for Letters {
+impl<T: PixelDisplay + ?Sized> Animation<T> for Letters {
     fn setup(&mut self, _display: &mut T) {
         self.color = Pixel::new(255u8, 255u8, 255u8, 255u8);
     }
 
-    fn update(&mut self, display: &mut T, delta: f64, elapsed: f64) {
-        let font = Vec::from(include_bytes!("font.ttf") as &[u8]);
-        let mut font = FontCollection::from_bytes(font)
+    fn update(&mut self, display: &mut T, _delta: f64, elapsed: f64) {
+        let font = Vec::from(::FONT);
+        let font = FontCollection::from_bytes(font)
             .unwrap()
             .into_font()
             .unwrap();
-        let mut image = RgbImage::new(display.rows() as u32, display.cols() as u32);
+        let options = BlitOptions { alpha: AlphaMode::Opaque, white: self.color.w(), ..Default::default() };
+        let mut raster = Raster::with_options(display, 1, options);
         let scale = Scale {
-            x: 1.5 * display.rows() as f32,
-            y: 1.2 * display.cols() as f32,
+            x: 1.5 * display.cols() as f32,
+            y: 1.2 * display.rows() as f32,
         };
 
-        let mut c = ALPHA[(elapsed % 26.0) as usize];
+        let c = ALPHA[(elapsed % 26.0) as usize];
 
         draw_text_mut(
-            &mut image,
-            Rgb([255u8, 255u8, 255u8]),
+            raster.image_mut(),
+            Rgba([self.color.r(), self.color.g(), self.color.b(), 255]),
             0,
             0,
             scale,
@@ -50,16 +49,14 @@ impl<T: PixelDisplay> Animation<T> for Letters {
             &c.to_string(),
         );
 
-        for row in 0..display.rows() {
-            for col in 0..display.cols() {
-                let p = image.get_pixel(row as u32, col as u32);
-
-                display.set_at(row, col, Pixel::new(p[0], p[1], p[2], 255u8));
-            }
-        }
+        raster.blit(display);
     }
 
     fn is_finished(&self, _display: &mut T, elapsed: f64) -> bool {
         elapsed > 26.0
     }
+
+    fn name(&self) -> &str {
+        "letters"
+    }
 }
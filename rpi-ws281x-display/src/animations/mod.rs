@@ -1,3 +1,25 @@
 pub mod snider;
 pub mod aaron;
 pub mod letters;
+pub mod picture;
+pub mod slideshow;
+pub mod timed;
+
+use ::Animation;
+
+/// The names accepted by `by_name`.
+pub const NAMES: [&str; 5] = ["letters", "circle", "stripe", "fireflies", "basic"];
+
+/// Creates one of the built in animations from its name so that
+/// playlists can be described in config files and on the command line.
+pub fn by_name(name: &str) -> Option<Box<dyn Animation>> {
+    let animation: Box<dyn Animation> = match name {
+        "letters" => Box::new(letters::Letters::default()),
+        "circle" => Box::new(snider::CircleAnimation::default()),
+        "stripe" => Box::new(snider::StripeAnimation::default()),
+        "fireflies" => Box::new(aaron::Fireflies::default()),
+        "basic" => Box::new(snider::BasicAnimation::default()),
+        _ => return None,
+    };
+    Some(animation)
+}
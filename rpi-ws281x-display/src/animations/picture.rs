@@ -0,0 +1,285 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use image::gif::Decoder as GifDecoder;
+use image::{self, AnimationDecoder, ImageFormat, ImageResult, Rgba, RgbaImage};
+
+use raster::{self, Filter};
+use ::{AlphaMode, Animation, BlitOptions, Pixel, PixelDisplay};
+
+/// How long a still image shows for, counting as one loop.
+const STILL_SECONDS: f64 = 10.0;
+/// GIF frames with a delay of at most `FAST_DELAY` are shown for this long
+/// instead, the same as browsers do.
+const MIN_DELAY: f64 = 0.1;
+const FAST_DELAY: f64 = 0.01;
+
+/// How a picture is sized to the display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FitMode {
+    /// As large as it goes while all of it still shows, with black bars
+    /// along the sides that are left over.
+    Fit,
+    /// Covers the whole display, cutting off whatever doesn't fit.
+    Fill,
+    /// Covers the whole display, squashed or stretched as needed.
+    Stretch,
+    /// The original size, in the middle of the display.
+    Center,
+}
+
+/// Shows a still image or plays an animated GIF.
+///
+/// ```no_run
+/// use rpi_ws281x_display::animations::picture::{FitMode, Picture};
+///
+/// let logo = Picture::open("logo.gif").unwrap().fit(FitMode::Fill).loops(3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Picture {
+    /// Every frame as decoded, with how many seconds it shows for.
+    source: Vec<(RgbaImage, f64)>,
+    fit: FitMode,
+    filter: Filter,
+    loops: u32,
+    /// The source frames sized for `size`.
+    frames: Vec<RgbaImage>,
+    size: (usize, usize),
+}
+
+impl Picture {
+    /// Loads a PNG, JPEG, GIF or any other format the `image` crate knows.
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Picture> {
+        Picture::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> ImageResult<Picture> {
+        if image::guess_format(bytes)? == ImageFormat::GIF {
+            let frames = GifDecoder::new(Cursor::new(bytes))?.into_frames().collect_frames()?;
+            let frames = frames.into_iter()
+                .map(|frame| {
+                    let delay = frame.delay();
+                    let seconds = f64::from(*delay.numer()) / f64::from(*delay.denom()) / 1000.0;
+                    (frame.into_buffer(), seconds)
+                })
+                .collect();
+            Ok(Picture::from_frames(frames))
+        } else {
+            let image = image::load_from_memory(bytes)?.to_rgba();
+            Ok(Picture::from_frames(vec![(image, STILL_SECONDS)]))
+        }
+    }
+
+    /// A picture from frames that were already decoded, each with how
+    /// many seconds it shows for.
+    ///
+    /// # Panics
+    /// When there are no frames.
+    pub fn from_frames(frames: Vec<(RgbaImage, f64)>) -> Picture {
+        assert!(!frames.is_empty(), "a picture needs at least one frame");
+        let source = frames.into_iter()
+            .map(|(image, seconds)| (image, if seconds <= FAST_DELAY { MIN_DELAY } else { seconds }))
+            .collect();
+        Picture {
+            source,
+            fit: FitMode::Fit,
+            filter: Filter::Area,
+            loops: 1,
+            frames: Vec::new(),
+            size: (0, 0),
+        }
+    }
+
+    /// Fits the whole picture on the display by default.
+    pub fn fit(mut self, fit: FitMode) -> Self {
+        self.fit = fit;
+        self.frames.clear();
+        self
+    }
+
+    /// Shrinks pictures by averaging by default, which suits photos and
+    /// logos. `Filter::Nearest` keeps pixel art crisp.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self.frames.clear();
+        self
+    }
+
+    /// How many times to play the picture before it's finished, once by
+    /// default or forever for 0. A still image counts as a ten second loop.
+    pub fn loops(mut self, loops: u32) -> Self {
+        self.loops = loops;
+        self
+    }
+
+    /// How long one loop takes, in seconds.
+    pub fn duration(&self) -> f64 {
+        self.source.iter().map(|&(_, seconds)| seconds).sum()
+    }
+
+    /// The index of the frame showing at `elapsed` seconds.
+    fn frame_at(&self, elapsed: f64) -> usize {
+        let mut time = elapsed.max(0.0) % self.duration();
+        for (i, &(_, seconds)) in self.source.iter().enumerate() {
+            if time < seconds {
+                return i;
+            }
+            time -= seconds;
+        }
+        self.source.len() - 1
+    }
+
+    /// The frame showing at `elapsed` seconds, fitted to a display of
+    /// `cols` by `rows` and transparent where the picture doesn't cover it.
+    pub(crate) fn frame(&mut self, cols: usize, rows: usize, elapsed: f64) -> &RgbaImage {
+        self.prepare((cols, rows));
+        let index = self.frame_at(elapsed);
+        &self.frames[index]
+    }
+
+    /// Sizes every frame for the display, if that hasn't been done yet.
+    fn prepare(&mut self, size: (usize, usize)) {
+        if size == self.size && !self.frames.is_empty() {
+            return;
+        }
+        self.size = size;
+        self.frames = self.source.iter()
+            .map(|(image, _)| fit_image(image, size.0 as u32, size.1 as u32, self.fit, self.filter))
+            .collect();
+    }
+}
+
+/// Scales `image` as `fit` says and puts it in the middle of a transparent
+/// image that is `width` by `height`.
+fn fit_image(image: &RgbaImage, width: u32, height: u32, fit: FitMode, filter: Filter) -> RgbaImage {
+    if fit == FitMode::Stretch {
+        return raster::resize(image, width, height, filter);
+    }
+    let (w, h) = (f64::from(image.width()), f64::from(image.height()));
+    let scale = match fit {
+        FitMode::Fit => (f64::from(width) / w).min(f64::from(height) / h),
+        FitMode::Fill => (f64::from(width) / w).max(f64::from(height) / h),
+        _ => 1.0,
+    };
+    let size = |len: f64| ((len * scale).round() as u32).max(1);
+    let scaled = raster::resize(image, size(w), size(h), filter);
+
+    let mut canvas = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+    let left = (i64::from(width) - i64::from(scaled.width())) / 2;
+    let top = (i64::from(height) - i64::from(scaled.height())) / 2;
+    for (x, y, p) in scaled.enumerate_pixels() {
+        let (cx, cy) = (left + i64::from(x), top + i64::from(y));
+        if cx >= 0 && cy >= 0 && cx < i64::from(width) && cy < i64::from(height) {
+            canvas.put_pixel(cx as u32, cy as u32, *p);
+        }
+    }
+    canvas
+}
+
+impl<T: PixelDisplay + ?Sized> Animation<T> for Picture {
+    fn setup(&mut self, display: &mut T) {
+        self.prepare((display.cols(), display.rows()));
+    }
+
+    fn update(&mut self, display: &mut T, _delta: f64, elapsed: f64) {
+        let frame = self.frame(display.cols(), display.rows(), elapsed);
+        display.fill(Pixel::default());
+        let options = BlitOptions { alpha: AlphaMode::Blend, ..Default::default() };
+        raster::blit(display, frame, &options);
+    }
+
+    fn is_finished(&self, _display: &mut T, elapsed: f64) -> bool {
+        self.loops > 0 && elapsed >= f64::from(self.loops) * self.duration()
+    }
+
+    fn name(&self) -> &str {
+        "picture"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gif;
+    use ::MemoryDisplay;
+
+    fn solid(width: u32, height: u32, r: u8) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([r, 0, 0, 255]))
+    }
+
+    /// The red channel of every pixel, row by row.
+    fn reds(display: &MemoryDisplay) -> Vec<Vec<u8>> {
+        display.pixels().map(|row| row.iter().map(|p| p.r()).collect()).collect()
+    }
+
+    #[test]
+    fn test_fit_modes() {
+        let image = RgbaImage::from_fn(2, 1, |x, _| Rgba([100 + x as u8, 0, 0, 255]));
+        let mut display = MemoryDisplay::new(4, 4);
+        let show = |display: &mut MemoryDisplay, fit| {
+            let mut picture = Picture::from_frames(vec![(image.clone(), 1.0)]).fit(fit).filter(Filter::Nearest);
+            picture.setup(display);
+            picture.update(display, 0.0, 0.0);
+            reds(display)
+        };
+
+        assert_eq!(show(&mut display, FitMode::Fit), vec![
+            vec![0, 0, 0, 0],
+            vec![100, 100, 101, 101],
+            vec![100, 100, 101, 101],
+            vec![0, 0, 0, 0],
+        ]);
+        assert_eq!(show(&mut display, FitMode::Fill), vec![vec![100, 100, 101, 101]; 4]);
+        assert_eq!(show(&mut display, FitMode::Stretch), vec![vec![100, 100, 101, 101]; 4]);
+        assert_eq!(show(&mut display, FitMode::Center)[1], vec![0, 100, 101, 0]);
+
+        let mut display = MemoryDisplay::new(2, 4);
+        assert_eq!(show(&mut display, FitMode::Fill), vec![vec![100, 101]; 4]);
+    }
+
+    #[test]
+    fn test_frame_delays_and_loops() {
+        // only delays of 10ms or less are stretched, 20ms ones are kept
+        let frames = vec![
+            (solid(1, 1, 1), 0.5),
+            (solid(1, 1, 2), 0.0),
+            (solid(1, 1, 3), 0.01),
+            (solid(1, 1, 4), 0.02),
+            (solid(1, 1, 5), 1.0),
+        ];
+        let mut picture = Picture::from_frames(frames).loops(2);
+        let mut display = MemoryDisplay::new(1, 1);
+        assert!((picture.duration() - 1.72).abs() < 1e-9);
+
+        picture.setup(&mut display);
+        let timeline = [(0.0, 1), (0.55, 2), (0.65, 3), (0.71, 4), (0.73, 5), (1.8, 1), (2.27, 2)];
+        for &(elapsed, red) in timeline.iter() {
+            picture.update(&mut display, 0.0, elapsed);
+            assert_eq!(display.get_at(0, 0).r(), red, "at {}s", elapsed);
+        }
+        assert!(!picture.is_finished(&mut display, 3.4));
+        assert!(picture.is_finished(&mut display, 3.5));
+        assert!(!picture.loops(0).is_finished(&mut display, 100.0));
+    }
+
+    #[test]
+    fn test_decodes_gifs() {
+        // GIF delays are in hundredths of a second
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, 2, 2, &[]).unwrap();
+            for &(r, delay) in [(255, 0), (0, 1), (0, 2)].iter() {
+                let mut frame = gif::Frame::from_rgb(2, 2, &[r, 0, 0].repeat(4));
+                frame.delay = delay;
+                encoder.write_frame(&frame).unwrap();
+            }
+        }
+        let picture = Picture::from_bytes(&bytes).unwrap();
+        assert_eq!(picture.source.len(), 3);
+        assert!((picture.duration() - (2.0 * MIN_DELAY + 0.02)).abs() < 1e-9);
+        assert_eq!(picture.source[0].0.get_pixel(1, 1)[0], 255);
+
+        assert!(Picture::from_bytes(b"not an image").is_err());
+    }
+}
@@ -0,0 +1,398 @@
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+use image::{Rgba, RgbaImage};
+
+use animations::picture::{FitMode, Picture};
+use raster::{self, Filter};
+use ::{AlphaMode, Animation, BlitOptions, PixelDisplay};
+
+/// The file extensions picked up from the directory, in lower case.
+const EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "bmp"];
+/// How long before a slide is up the next one starts loading, which gives
+/// big images time to decode while still picking up ones added meanwhile.
+const PRELOAD_SECONDS: f64 = 2.0;
+
+/// How one slide gives way to the next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transition {
+    /// Switches straight away.
+    Cut,
+    /// Fades from one slide into the next.
+    Crossfade,
+    /// Pushes the old slide out to the left as the new one comes in from
+    /// the right.
+    Slide,
+}
+
+struct Slide {
+    path: PathBuf,
+    picture: Picture,
+    /// When it started showing, in seconds since setup.
+    start: f64,
+}
+
+/// An image that was decoded for the next slide.
+type Loaded = (PathBuf, Picture);
+
+/// The slide after the current one, which is decoded on another thread so
+/// the frames keep coming while it loads.
+enum Next {
+    Idle,
+    /// Receives the images in the directory and the slide, if any of them
+    /// could be decoded.
+    Loading(Receiver<(Vec<PathBuf>, Option<Loaded>)>),
+    Ready(Option<Loaded>),
+}
+
+/// Shows every image in a directory in turn, in order of their file names.
+/// The directory is looked at again every so often, so images can be added
+/// and removed while it runs. Images are read and decoded in the
+/// background, a little before they are due.
+///
+/// It never finishes by itself; wrap it in `Timed` to give it a slot in a
+/// playlist.
+///
+/// ```no_run
+/// use rpi_ws281x_display::animations::slideshow::{Slideshow, Transition};
+///
+/// let slideshow = Slideshow::new("/home/pi/artwork")
+///     .slide_seconds(8.0)
+///     .transition(Transition::Slide, 1.0);
+/// ```
+pub struct Slideshow {
+    dir: PathBuf,
+    slide_seconds: f64,
+    transition: Transition,
+    transition_seconds: f64,
+    rescan_seconds: f64,
+    fit: FitMode,
+    filter: Filter,
+    paths: Vec<PathBuf>,
+    last_scan: Option<f64>,
+    current: Option<Slide>,
+    previous: Option<Slide>,
+    next: Next,
+}
+
+impl Slideshow {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Slideshow {
+            dir: dir.into(),
+            slide_seconds: 10.0,
+            transition: Transition::Crossfade,
+            transition_seconds: 1.0,
+            rescan_seconds: 10.0,
+            fit: FitMode::Fit,
+            filter: Filter::Area,
+            paths: Vec::new(),
+            last_scan: None,
+            current: None,
+            previous: None,
+            next: Next::Idle,
+        }
+    }
+
+    /// How long each image shows for, transition included. 10 seconds by
+    /// default.
+    pub fn slide_seconds(mut self, seconds: f64) -> Self {
+        self.slide_seconds = seconds;
+        self
+    }
+
+    /// A one second crossfade by default.
+    pub fn transition(mut self, transition: Transition, seconds: f64) -> Self {
+        self.transition = transition;
+        self.transition_seconds = seconds;
+        self
+    }
+
+    /// How often to look for new images, every 10 seconds by default.
+    pub fn rescan_seconds(mut self, seconds: f64) -> Self {
+        self.rescan_seconds = seconds;
+        self
+    }
+
+    /// How every image is sized, see `Picture::fit`.
+    pub fn fit(mut self, fit: FitMode) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// See `Picture::filter`.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    fn rescan_due(&self, elapsed: f64) -> bool {
+        self.last_scan.is_none_or(|last| elapsed - last >= self.rescan_seconds)
+    }
+
+    /// Starts loading the image after the current one for a display of
+    /// `cols` by `rows`, looking at the directory again first when it's
+    /// time to.
+    fn preload(&mut self, elapsed: f64, cols: usize, rows: usize) {
+        let dir = if self.rescan_due(elapsed) {
+            self.last_scan = Some(elapsed);
+            Some(self.dir.clone())
+        } else {
+            None
+        };
+        let paths = self.paths.clone();
+        let after = self.current.as_ref().map(|slide| slide.path.clone());
+        let (fit, filter) = (self.fit, self.filter);
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let paths = dir.map_or(paths, |dir| scan(&dir));
+            let next = load_after(&paths, after.as_ref(), fit, filter, cols, rows);
+            // nobody is waiting for it any more after a setup
+            let _ = tx.send((paths, next));
+        });
+        self.next = Next::Loading(rx);
+    }
+
+    /// Picks up the next slide if it has finished loading.
+    fn receive(&mut self) {
+        let received = match self.next {
+            Next::Loading(ref rx) => rx.try_recv(),
+            _ => return,
+        };
+        match received {
+            Ok((paths, next)) => {
+                self.paths = paths;
+                self.next = Next::Ready(next);
+            }
+            Err(TryRecvError::Empty) => {}
+            // decoding panicked, so there's nothing to show
+            Err(TryRecvError::Disconnected) => self.next = Next::Ready(None),
+        }
+    }
+
+    /// How far through the transition into the current slide, from 0 to 1.
+    fn progress(&self, elapsed: f64) -> f64 {
+        match self.current {
+            Some(ref slide) if self.transition_seconds > 0.0 && self.transition != Transition::Cut => {
+                ((elapsed - slide.start) / self.transition_seconds).min(1.0)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+/// The first image after `after` that can be decoded, starting over from
+/// the first image after the last, already fitted to a display of `cols`
+/// by `rows`.
+fn load_after(
+    paths: &[PathBuf],
+    after: Option<&PathBuf>,
+    fit: FitMode,
+    filter: Filter,
+    cols: usize,
+    rows: usize,
+) -> Option<Loaded> {
+    let start = after.map_or(0, |after| paths.iter().position(|path| path > after).unwrap_or(0));
+    for path in paths.iter().cycle().skip(start).take(paths.len()) {
+        if let Ok(picture) = Picture::open(path) {
+            let mut picture = picture.fit(fit).filter(filter);
+            picture.frame(cols, rows, 0.0);
+            return Some((path.clone(), picture));
+        }
+    }
+    None
+}
+
+/// The images in `dir`, sorted by path. A directory that can't be read
+/// has none.
+fn scan(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    paths.retain(|path| {
+        path.is_file() && path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+    });
+    paths.sort();
+    paths
+}
+
+/// The image with its see-through parts turned black.
+fn flatten(image: &RgbaImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let p = image.get_pixel(x, y);
+        let scale = |c: u8| ((u16::from(c) * u16::from(p[3]) + 127) / 255) as u8;
+        Rgba([scale(p[0]), scale(p[1]), scale(p[2]), 255])
+    })
+}
+
+fn crossfade(from: &RgbaImage, to: &RgbaImage, t: f64) -> RgbaImage {
+    RgbaImage::from_fn(to.width(), to.height(), |x, y| {
+        let (a, b) = (from.get_pixel(x, y), to.get_pixel(x, y));
+        let mix = |c: usize| (f64::from(a[c]) + (f64::from(b[c]) - f64::from(a[c])) * t).round() as u8;
+        Rgba([mix(0), mix(1), mix(2), 255])
+    })
+}
+
+fn slide(from: &RgbaImage, to: &RgbaImage, t: f64) -> RgbaImage {
+    let width = to.width();
+    let shift = (f64::from(width) * t).round() as u32;
+    RgbaImage::from_fn(width, to.height(), |x, y| {
+        if x + shift < width {
+            *from.get_pixel(x + shift, y)
+        } else {
+            *to.get_pixel(x + shift - width, y)
+        }
+    })
+}
+
+impl<T: PixelDisplay + ?Sized> Animation<T> for Slideshow {
+    fn setup(&mut self, display: &mut T) {
+        self.current = None;
+        self.previous = None;
+        self.last_scan = None;
+        self.preload(0.0, display.cols(), display.rows());
+    }
+
+    fn update(&mut self, display: &mut T, _delta: f64, elapsed: f64) {
+        let (cols, rows) = (display.cols(), display.rows());
+        self.receive();
+        let due = self.current.as_ref().is_none_or(|slide| elapsed - slide.start >= self.slide_seconds);
+        if due {
+            if let Next::Ready(next) = mem::replace(&mut self.next, Next::Idle) {
+                match next {
+                    Some((path, picture)) => {
+                        self.previous = self.current.replace(Slide { path, picture, start: elapsed });
+                    }
+                    // nothing left that can be shown
+                    None => {
+                        self.previous = None;
+                        self.current = None;
+                    }
+                }
+            }
+        }
+        if let Next::Idle = self.next {
+            let preload = match self.current {
+                Some(ref slide) => elapsed - slide.start >= self.slide_seconds - PRELOAD_SECONDS,
+                // nothing showing means nothing could be, until the
+                // directory changes
+                None => self.rescan_due(elapsed),
+            };
+            if preload {
+                self.preload(elapsed, cols, rows);
+            }
+        }
+
+        let t = self.progress(elapsed);
+        let image = match self.current {
+            Some(ref mut current) => {
+                let to = flatten(current.picture.frame(cols, rows, elapsed - current.start));
+                match self.previous {
+                    Some(ref mut previous) if t < 1.0 => {
+                        let from = flatten(previous.picture.frame(cols, rows, elapsed - previous.start));
+                        match self.transition {
+                            Transition::Slide => slide(&from, &to, t),
+                            _ => crossfade(&from, &to, t),
+                        }
+                    }
+                    _ => to,
+                }
+            }
+            None => RgbaImage::new(cols as u32, rows as u32),
+        };
+        if t >= 1.0 {
+            self.previous = None;
+        }
+        let options = BlitOptions { alpha: AlphaMode::Opaque, ..Default::default() };
+        raster::blit(display, &image, &options);
+    }
+
+    fn is_finished(&self, _display: &mut T, _elapsed: f64) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str {
+        "slideshow"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+    use std::time::Duration;
+    use ::MemoryDisplay;
+
+    fn save(dir: &Path, name: &str, r: u8, b: u8) {
+        RgbaImage::from_pixel(2, 2, Rgba([r, 0, b, 255])).save(dir.join(name)).unwrap();
+    }
+
+    fn color(display: &MemoryDisplay, x: usize) -> (u8, u8) {
+        let p = display.get_at(x, 0);
+        (p.r(), p.b())
+    }
+
+    /// Waits for the next slide to finish loading.
+    fn wait(slideshow: &mut Slideshow) {
+        while let Next::Loading(_) = slideshow.next {
+            thread::sleep(Duration::from_millis(1));
+            slideshow.receive();
+        }
+    }
+
+    #[test]
+    fn test_slides_and_rescans() {
+        let dir = env::temp_dir().join(format!("slideshow-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        save(&dir, "a.png", 200, 0);
+        save(&dir, "b.png", 0, 200);
+        fs::write(dir.join("notes.txt"), "not an image").unwrap();
+        fs::write(dir.join("broken.png"), "not an image either").unwrap();
+
+        let mut display = MemoryDisplay::new(2, 2);
+        let mut slideshow = Slideshow::new(&dir)
+            .slide_seconds(1.0)
+            .transition(Transition::Crossfade, 0.5)
+            .rescan_seconds(0.0);
+        slideshow.setup(&mut display);
+
+        wait(&mut slideshow);
+        slideshow.update(&mut display, 0.0, 0.0);
+        assert_eq!(color(&display, 0), (200, 0));
+
+        // new images are picked up when the slide after them loads, ones
+        // that can't be decoded are skipped
+        wait(&mut slideshow);
+        save(&dir, "c.png", 50, 50);
+        slideshow.update(&mut display, 0.0, 1.0);
+        assert_eq!(color(&display, 0), (200, 0));
+        slideshow.update(&mut display, 0.0, 1.25);
+        assert_eq!(color(&display, 0), (100, 100));
+        slideshow.update(&mut display, 0.0, 1.5);
+        assert_eq!(color(&display, 0), (0, 200));
+
+        slideshow = slideshow.transition(Transition::Slide, 0.5);
+        wait(&mut slideshow);
+        slideshow.update(&mut display, 0.0, 2.0);
+        slideshow.update(&mut display, 0.0, 2.25);
+        assert_eq!((color(&display, 0), color(&display, 1)), ((0, 200), (50, 50)));
+
+        // a slide that already loaded still shows after its image is gone
+        wait(&mut slideshow);
+        fs::remove_dir_all(&dir).unwrap();
+        slideshow.update(&mut display, 0.0, 3.0);
+        assert_eq!(color(&display, 1), (50, 50));
+        slideshow.update(&mut display, 0.0, 3.5);
+        assert_eq!(color(&display, 0), (200, 0));
+
+        wait(&mut slideshow);
+        slideshow.update(&mut display, 0.0, 4.5);
+        assert_eq!(color(&display, 0), (0, 0));
+    }
+}
@@ -34,19 +34,14 @@ mod helpers {
 
     impl From<Color> for Pixel {
         fn from(c: Color) -> Self {
-            Pixel::new(
-                (c.x * 255.0).round().min(255.0).max(0.0) as u8,
-                (c.y * 255.0).round().min(255.0).max(0.0) as u8,
-                (c.z * 255.0).round().min(255.0).max(0.0) as u8,
-                (c.w * 255.0).round().min(255.0).max(0.0) as u8,
-            )
+            Pixel::from_f32(c.x, c.y, c.z, c.w)
         }
     }
 
-    pub fn super_sample_to_display<S: Sample, D: PixelDisplay>(sample: &S, elapsed: f64, display: &mut D, density: i32) {
+    pub fn super_sample_to_display<S: Sample, D: PixelDisplay + ?Sized>(sample: &S, elapsed: f64, display: &mut D, density: i32) {
         let samples_per_pixel = density * density;
-        for y in 0usize..7 {
-            for x in 0usize..7 {
+        for (y, row) in display.pixels_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
                 let mut color = Color::new(0.0, 0.0, 0.0, 0.0);
                 for j in 1..density + 1 {
                     for i in 1..density + 1 {
@@ -58,7 +53,7 @@ mod helpers {
                     }
                 }
                 color /= samples_per_pixel as f32;
-                display.set_at(x, y, color.into());
+                *pixel = color.into();
             }
         }
     }
@@ -76,7 +71,7 @@ impl Default for BasicAnimation {
     }
 }
 
-impl<D: PixelDisplay> Animation<D> for BasicAnimation {
+impl<D: PixelDisplay + ?Sized> Animation<D> for BasicAnimation {
     fn setup(&mut self, _display: &mut D) {
         self.i = 0;
     }
@@ -100,6 +95,10 @@ impl<D: PixelDisplay> Animation<D> for BasicAnimation {
     fn is_finished(&self, _display: &mut D, elapsed: f64) -> bool {
         4.9 < elapsed
     }
+
+    fn name(&self) -> &str {
+        "basic"
+    }
 }
 
 // Circle Animation
@@ -135,7 +134,7 @@ impl Sample for CircleAnimation {
     }
 }
 
-impl<D: PixelDisplay> Animation<D> for CircleAnimation {
+impl<D: PixelDisplay + ?Sized> Animation<D> for CircleAnimation {
     fn setup(&mut self, _display: &mut D) {
         *self = CircleAnimation::default();
     }
@@ -149,6 +148,10 @@ impl<D: PixelDisplay> Animation<D> for CircleAnimation {
     fn is_finished(&self, _display: &mut D, elapsed: f64) -> bool {
         5.0 < elapsed
     }
+
+    fn name(&self) -> &str {
+        "circle"
+    }
 }
 
 // Stripe Animation
@@ -187,7 +190,7 @@ impl Sample for StripeAnimation {
     }
 }
 
-impl<D: PixelDisplay> Animation<D> for StripeAnimation {
+impl<D: PixelDisplay + ?Sized> Animation<D> for StripeAnimation {
     fn setup(&mut self, _display: &mut D) {
         self.transform = Mat3::identity();
     }
@@ -208,4 +211,8 @@ impl<D: PixelDisplay> Animation<D> for StripeAnimation {
     fn is_finished(&self, _display: &mut D, elapsed: f64) -> bool {
         10.0 < elapsed
     }
+
+    fn name(&self) -> &str {
+        "stripe"
+    }
 }
@@ -0,0 +1,32 @@
+use ::{Animation, PixelDisplay};
+
+/// Wraps another animation and finishes it after a fixed number of
+/// seconds instead of when the animation itself decides to.
+pub struct Timed<D: PixelDisplay + ?Sized = dyn PixelDisplay> {
+    animation: Box<dyn Animation<D>>,
+    duration: f64,
+}
+
+impl<D: PixelDisplay + ?Sized> Timed<D> {
+    pub fn new(animation: Box<dyn Animation<D>>, duration: f64) -> Self {
+        Timed { animation, duration }
+    }
+}
+
+impl<D: PixelDisplay + ?Sized> Animation<D> for Timed<D> {
+    fn setup(&mut self, display: &mut D) {
+        self.animation.setup(display);
+    }
+
+    fn update(&mut self, display: &mut D, delta: f64, elapsed: f64) {
+        self.animation.update(display, delta, elapsed);
+    }
+
+    fn is_finished(&self, _display: &mut D, elapsed: f64) -> bool {
+        elapsed >= self.duration
+    }
+
+    fn name(&self) -> &str {
+        self.animation.name()
+    }
+}
@@ -0,0 +1,531 @@
+use std::mem;
+
+use ::{Pixel, PixelDisplay};
+
+/// An image to `blit` onto a display, with an opacity for every pixel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sprite {
+    width: usize,
+    height: usize,
+    pixels: Vec<Pixel>,
+    alpha: Vec<u8>,
+}
+
+impl Sprite {
+    /// An opaque sprite from its pixels, row by row.
+    ///
+    /// # Panics
+    /// When there aren't exactly `width * height` pixels.
+    pub fn new(width: usize, height: usize, pixels: Vec<Pixel>) -> Self {
+        assert_eq!(pixels.len(), width * height, "a {}x{} sprite needs {} pixels", width, height, width * height);
+        Sprite { width, height, alpha: vec![255; pixels.len()], pixels }
+    }
+
+    /// A sprite that is see-through wherever a pixel is `None`.
+    ///
+    /// # Panics
+    /// When there aren't exactly `width * height` pixels.
+    pub fn from_mask(width: usize, height: usize, pixels: &[Option<Pixel>]) -> Self {
+        let alpha = pixels.iter().map(|p| if p.is_some() { 255 } else { 0 }).collect();
+        let pixels = pixels.iter().map(|p| p.unwrap_or_default()).collect();
+        Sprite::new(width, height, pixels).with_alpha(alpha)
+    }
+
+    /// Sets the opacity of every pixel, row by row, from 0 for invisible
+    /// to 255 for opaque.
+    ///
+    /// # Panics
+    /// When there isn't one value per pixel.
+    pub fn with_alpha(mut self, alpha: Vec<u8>) -> Self {
+        assert_eq!(alpha.len(), self.pixels.len(), "a {}x{} sprite needs {} alpha values", self.width, self.height, self.pixels.len());
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixel at `x`, `y` and its opacity.
+    pub fn get(&self, x: usize, y: usize) -> (Pixel, u8) {
+        let idx = x + y * self.width;
+        (self.pixels[idx], self.alpha[idx])
+    }
+}
+
+fn fpart(x: f32) -> f32 {
+    x - x.floor()
+}
+
+fn rfpart(x: f32) -> f32 {
+    1.0 - fpart(x)
+}
+
+/// `value` brought within the range of an `i32`, which keeps coordinates
+/// that are off the display off it.
+fn saturate(value: i64) -> i32 {
+    value.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32
+}
+
+/// Whether the box from `left`, `top` to `right`, `bottom`, all included,
+/// is entirely off a display of `cols` by `rows`.
+fn misses(cols: usize, rows: usize, left: i64, top: i64, right: i64, bottom: i64) -> bool {
+    right < 0 || bottom < 0 || left >= cols as i64 || top >= rows as i64
+}
+
+/// Clips the line from `x0`, `y0` to `x1`, `y1` to a display of `cols` by
+/// `rows` using the Cohen–Sutherland algorithm, or returns `None` when it
+/// misses the display. Ends that are already on it stay where they are.
+fn clip_line(x0: i32, y0: i32, x1: i32, y1: i32, cols: usize, rows: usize) -> Option<(i32, i32, i32, i32)> {
+    const LEFT: u8 = 1;
+    const RIGHT: u8 = 2;
+    const TOP: u8 = 4;
+    const BOTTOM: u8 = 8;
+    if cols == 0 || rows == 0 {
+        return None;
+    }
+    let (right, bottom) = ((cols - 1) as f64, (rows - 1) as f64);
+    let outcode = |x: f64, y: f64| {
+        let horizontal = if x < 0.0 { LEFT } else if x > right { RIGHT } else { 0 };
+        let vertical = if y < 0.0 { TOP } else if y > bottom { BOTTOM } else { 0 };
+        horizontal | vertical
+    };
+
+    let (mut x0, mut y0, mut x1, mut y1) = (f64::from(x0), f64::from(y0), f64::from(x1), f64::from(y1));
+    let (mut code0, mut code1) = (outcode(x0, y0), outcode(x1, y1));
+    loop {
+        if code0 | code1 == 0 {
+            return Some((x0.round() as i32, y0.round() as i32, x1.round() as i32, y1.round() as i32));
+        }
+        if code0 & code1 != 0 {
+            return None;
+        }
+        // move the end that is off the display onto the edge it is beyond
+        let code = if code0 != 0 { code0 } else { code1 };
+        let (x, y) = if code & TOP != 0 {
+            (x0 + (x1 - x0) * -y0 / (y1 - y0), 0.0)
+        } else if code & BOTTOM != 0 {
+            (x0 + (x1 - x0) * (bottom - y0) / (y1 - y0), bottom)
+        } else if code & LEFT != 0 {
+            (0.0, y0 + (y1 - y0) * -x0 / (x1 - x0))
+        } else {
+            (right, y0 + (y1 - y0) * (right - x0) / (x1 - x0))
+        };
+        if code == code0 {
+            x0 = x;
+            y0 = y;
+            code0 = outcode(x0, y0);
+        } else {
+            x1 = x;
+            y1 = y;
+            code1 = outcode(x1, y1);
+        }
+    }
+}
+
+/// Drawing primitives for every `PixelDisplay`.
+///
+/// Coordinates are signed so that shapes may hang off the edges of the
+/// display; whatever falls outside of it is clipped.
+pub trait Canvas: PixelDisplay {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.cols() && (y as usize) < self.rows()
+    }
+
+    /// Sets a single pixel, if it is on the display.
+    fn plot(&mut self, x: i32, y: i32, pixel: Pixel) {
+        if self.contains(x, y) {
+            self.set_at(x as usize, y as usize, pixel);
+        }
+    }
+
+    /// Draws `pixel` over what is already at `x`, `y` with an opacity from
+    /// 0 to 255.
+    fn blend_at(&mut self, x: i32, y: i32, pixel: Pixel, alpha: u8) {
+        if alpha > 0 && self.contains(x, y) {
+            let (x, y) = (x as usize, y as usize);
+            let under = self.get_at(x, y);
+            self.set_at(x, y, under.blend(pixel, alpha));
+        }
+    }
+
+    /// A horizontal run of pixels from `x0` to `x1`, both included.
+    fn hline(&mut self, x0: i32, x1: i32, y: i32, pixel: Pixel) {
+        if y < 0 || y as usize >= self.rows() || self.cols() == 0 {
+            return;
+        }
+        let (x0, x1) = (x0.min(x1).max(0), x0.max(x1).min(self.cols() as i32 - 1));
+        for x in x0..=x1 {
+            self.set_at(x as usize, y as usize, pixel);
+        }
+    }
+
+    /// A vertical run of pixels from `y0` to `y1`, both included.
+    fn vline(&mut self, x: i32, y0: i32, y1: i32, pixel: Pixel) {
+        if x < 0 || x as usize >= self.cols() || self.rows() == 0 {
+            return;
+        }
+        let (y0, y1) = (y0.min(y1).max(0), y0.max(y1).min(self.rows() as i32 - 1));
+        for y in y0..=y1 {
+            self.set_at(x as usize, y as usize, pixel);
+        }
+    }
+
+    /// A one pixel wide line, using Bresenham's algorithm on the part of
+    /// it that is on the display.
+    fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, pixel: Pixel) {
+        let (x0, y0, x1, y1) = match clip_line(x0, y0, x1, y1, self.cols(), self.rows()) {
+            Some(clipped) => clipped,
+            None => return,
+        };
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let mut err = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            self.plot(x, y, pixel);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// An anti-aliased line using Xiaolin Wu's algorithm, which blends the
+    /// color into the two pixels nearest to the line. Pixel centers are at
+    /// whole coordinates.
+    fn line_aa(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, pixel: Pixel) {
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        let (mut x0, mut y0, mut x1, mut y1) = if steep { (y0, x0, y1, x1) } else { (x0, y0, x1, y1) };
+        if x0 > x1 {
+            mem::swap(&mut x0, &mut x1);
+            mem::swap(&mut y0, &mut y1);
+        }
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+        let plot = |canvas: &mut Self, x: i32, y: i32, coverage: f32| {
+            let (x, y) = if steep { (y, x) } else { (x, y) };
+            canvas.blend_at(x, y, pixel, (coverage.clamp(0.0, 1.0) * 255.0).round() as u8);
+        };
+
+        let xend = x0.round();
+        let yend = y0 + gradient * (xend - x0);
+        let xgap = rfpart(x0 + 0.5);
+        let (xpxl1, ypxl1) = (xend as i32, yend.floor() as i32);
+        plot(self, xpxl1, ypxl1, rfpart(yend) * xgap);
+        plot(self, xpxl1, ypxl1.saturating_add(1), fpart(yend) * xgap);
+        let mut intery = yend + gradient;
+
+        let xend = x1.round();
+        let yend = y1 + gradient * (xend - x1);
+        let xgap = fpart(x1 + 0.5);
+        let (xpxl2, ypxl2) = (xend as i32, yend.floor() as i32);
+        plot(self, xpxl2, ypxl2, rfpart(yend) * xgap);
+        plot(self, xpxl2, ypxl2.saturating_add(1), fpart(yend) * xgap);
+
+        // only walk along the part of the line that is on the display
+        let length = if steep { self.rows() } else { self.cols() } as i32;
+        let first = xpxl1.saturating_add(1).max(0);
+        intery += gradient * (i64::from(first) - i64::from(xpxl1) - 1) as f32;
+        for x in first..xpxl2.min(length) {
+            let y = intery.floor() as i32;
+            plot(self, x, y, rfpart(intery));
+            plot(self, x, y.saturating_add(1), fpart(intery));
+            intery += gradient;
+        }
+    }
+
+    /// The outline of a rectangle with its top left corner at `x`, `y`.
+    fn rect(&mut self, x: i32, y: i32, width: i32, height: i32, pixel: Pixel) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let (right, bottom) = (x.saturating_add(width - 1), y.saturating_add(height - 1));
+        self.hline(x, right, y, pixel);
+        self.hline(x, right, bottom, pixel);
+        self.vline(x, y, bottom, pixel);
+        self.vline(right, y, bottom, pixel);
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, pixel: Pixel) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let (right, bottom) = (x.saturating_add(width - 1), y.saturating_add(height - 1));
+        for row in y.max(0)..=bottom.min(self.rows() as i32 - 1) {
+            self.hline(x, right, row, pixel);
+        }
+    }
+
+    /// The outline of a circle, using the midpoint algorithm.
+    fn circle(&mut self, cx: i32, cy: i32, radius: i32, pixel: Pixel) {
+        let (cx, cy, r) = (i64::from(cx), i64::from(cy), i64::from(radius));
+        if r < 0 || misses(self.cols(), self.rows(), cx - r, cy - r, cx + r, cy + r) {
+            return;
+        }
+        let (mut x, mut y, mut err) = (r, 0, 1 - r);
+        while x >= y {
+            for &(dx, dy) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)].iter() {
+                self.plot(saturate(cx + dx), saturate(cy + dy), pixel);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// A filled circle covering the same pixels as `circle` and everything
+    /// inside of it.
+    fn fill_circle(&mut self, cx: i32, cy: i32, radius: i32, pixel: Pixel) {
+        let (cx, cy, r) = (i64::from(cx), i64::from(cy), i64::from(radius));
+        if r < 0 || misses(self.cols(), self.rows(), cx - r, cy - r, cx + r, cy + r) {
+            return;
+        }
+        let (mut x, mut y, mut err) = (r, 0, 1 - r);
+        while x >= y {
+            for &(dx, dy) in [(x, y), (x, -y), (y, x), (y, -x)].iter() {
+                self.hline(saturate(cx - dx), saturate(cx + dx), saturate(cy + dy), pixel);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// The outline of a polygon, closed back to the first point.
+    fn polygon(&mut self, points: &[(i32, i32)], pixel: Pixel) {
+        for (i, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(i + 1) % points.len()];
+            self.line(x0, y0, x1, y1, pixel);
+        }
+    }
+
+    /// A filled polygon, including its outline. Overlapping parts of a
+    /// self-intersecting polygon are left empty.
+    fn fill_polygon(&mut self, points: &[(i32, i32)], pixel: Pixel) {
+        if points.is_empty() || self.rows() == 0 {
+            return;
+        }
+        let top = points.iter().map(|p| p.1).min().unwrap().max(0);
+        let bottom = points.iter().map(|p| p.1).max().unwrap().min(self.rows() as i32 - 1);
+        let mut crossings = Vec::new();
+        for y in top..=bottom {
+            crossings.clear();
+            for (i, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                // count every edge's top end but not its bottom one, so a
+                // shared vertex is only crossed once
+                if (y0 <= y && y < y1) || (y1 <= y && y < y0) {
+                    let t = (y - y0) as f32 / (y1 - y0) as f32;
+                    crossings.push(x0 as f32 + t * (x1 - x0) as f32);
+                }
+            }
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for pair in crossings.chunks(2).filter(|pair| pair.len() == 2) {
+                self.hline(pair[0].ceil() as i32, pair[1].floor() as i32, y, pixel);
+            }
+        }
+        self.polygon(points, pixel);
+    }
+
+    /// Replaces the color at `x`, `y` and every pixel of the same color
+    /// connected to it, horizontally or vertically, with `pixel`.
+    fn flood_fill(&mut self, x: i32, y: i32, pixel: Pixel) {
+        if !self.contains(x, y) {
+            return;
+        }
+        let target = self.get_at(x as usize, y as usize);
+        if target == pixel {
+            return;
+        }
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            if !self.contains(x, y) || self.get_at(x as usize, y as usize) != target {
+                continue;
+            }
+            self.set_at(x as usize, y as usize, pixel);
+            stack.extend_from_slice(&[(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]);
+        }
+    }
+
+    /// Fills the whole display with a gradient that goes from `from` at
+    /// `start` to `to` at `end`, and stays that color beyond them.
+    fn gradient(&mut self, start: (f32, f32), end: (f32, f32), from: Pixel, to: Pixel) {
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let len2 = dx * dx + dy * dy;
+        for (y, row) in self.pixels_mut().enumerate() {
+            for (x, p) in row.iter_mut().enumerate() {
+                let t = if len2 == 0.0 {
+                    0.0
+                } else {
+                    ((x as f32 - start.0) * dx + (y as f32 - start.1) * dy) / len2
+                };
+                *p = from.lerp(to, t);
+            }
+        }
+    }
+
+    /// Fills the whole display with a gradient from `inner` at the center
+    /// to `outer` at `radius` and beyond.
+    fn radial_gradient(&mut self, center: (f32, f32), radius: f32, inner: Pixel, outer: Pixel) {
+        for (y, row) in self.pixels_mut().enumerate() {
+            for (x, p) in row.iter_mut().enumerate() {
+                let distance = (x as f32 - center.0).hypot(y as f32 - center.1);
+                let t = if radius > 0.0 { distance / radius } else { 1.0 };
+                *p = inner.lerp(outer, t);
+            }
+        }
+    }
+
+    /// Draws a sprite with its top left corner at `x`, `y`, blending in
+    /// the pixels that aren't fully opaque.
+    fn blit(&mut self, sprite: &Sprite, x: i32, y: i32) {
+        let (x, y) = (i64::from(x), i64::from(y));
+        let (right, bottom) = (x + sprite.width() as i64 - 1, y + sprite.height() as i64 - 1);
+        if misses(self.cols(), self.rows(), x, y, right, bottom) {
+            return;
+        }
+        for sy in 0..sprite.height() {
+            for sx in 0..sprite.width() {
+                let (pixel, alpha) = sprite.get(sx, sy);
+                let (px, py) = (saturate(x + sx as i64), saturate(y + sy as i64));
+                if alpha == 255 {
+                    self.plot(px, py, pixel);
+                } else {
+                    self.blend_at(px, py, pixel, alpha);
+                }
+            }
+        }
+    }
+}
+
+impl<D: PixelDisplay + ?Sized> Canvas for D {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::MemoryDisplay;
+
+    fn on() -> Pixel {
+        Pixel::rgb(255, 255, 255)
+    }
+
+    /// Draws the display as text, `#` for lit pixels and `.` for dark ones.
+    fn show(display: &MemoryDisplay) -> Vec<String> {
+        display.pixels()
+            .map(|row| row.iter().map(|p| if *p == Pixel::default() { '.' } else { '#' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_lines_and_shapes() {
+        let mut display = MemoryDisplay::new(5, 5);
+        display.line(0, 0, 4, 2, on());
+        assert_eq!(show(&display), vec!["#....", ".##..", "...##", ".....", "....."]);
+
+        let mut display = MemoryDisplay::new(5, 5);
+        display.rect(-1, 1, 4, 3, on());
+        display.circle(4, 4, 1, on());
+        assert_eq!(show(&display), vec![".....", "###..", "..#..", "###.#", "...#."]);
+
+        let mut display = MemoryDisplay::new(5, 5);
+        display.fill_circle(2, 2, 2, on());
+        assert_eq!(show(&display), vec![".###.", "#####", "#####", "#####", ".###."]);
+
+        let mut display = MemoryDisplay::new(5, 5);
+        display.fill_polygon(&[(0, 0), (4, 0), (0, 4)], on());
+        assert_eq!(show(&display), vec!["#####", "####.", "###..", "##...", "#...."]);
+    }
+
+    #[test]
+    fn test_far_off_the_display() {
+        let mut display = MemoryDisplay::new(3, 3);
+        display.fill_rect(0, 1, 5, i32::MAX, on());
+        display.rect(i32::MIN, i32::MIN, i32::MAX, i32::MAX, on());
+        display.line(2, -1_000_000_000, 2, 1_000_000_000, on());
+        display.line(i32::MIN, i32::MIN, i32::MAX, i32::MAX, on());
+        display.line(-10, 0, -5, 2, on());
+        assert_eq!(show(&display), vec!["#.#", "###", "###"]);
+
+        let mut display = MemoryDisplay::new(3, 3);
+        display.line_aa(-1e9, 1.0, 1e9, 1.0, on());
+        display.line_aa(0.0, 1e30, 0.0, -1e30, on());
+        display.line_aa(f32::NAN, 0.0, 1.0, f32::INFINITY, on());
+        assert_eq!(show(&display)[1], "###");
+
+        // circles and sprites near the ends of the coordinates, and a
+        // circle too big to walk around that misses the display anyway
+        let mut display = MemoryDisplay::new(3, 3);
+        display.circle(i32::MAX - 1, 0, 5, on());
+        display.circle(i32::MIN, i32::MIN, i32::MAX, on());
+        display.fill_circle(1, i32::MIN + 1, 5, on());
+        display.fill_circle(i32::MAX, i32::MAX, i32::MAX / 2, on());
+        let sprite = Sprite::new(2, 2, vec![on(); 4]);
+        display.blit(&sprite, i32::MAX, 0);
+        display.blit(&sprite, i32::MIN, i32::MIN);
+        display.blit(&sprite, -1, -1);
+        assert_eq!(show(&display), vec!["#..", "...", "..."]);
+    }
+
+    #[test]
+    fn test_line_aa() {
+        let mut display = MemoryDisplay::new(5, 3);
+        display.line_aa(0.0, 1.0, 4.0, 1.0, on());
+        assert_eq!(display.get_at(2, 1), on());
+        assert_eq!(display.get_at(0, 1), Pixel::default().blend(on(), 128));
+        assert_eq!(display.get_at(2, 0), Pixel::default());
+
+        let mut display = MemoryDisplay::new(3, 3);
+        display.line_aa(0.0, 0.5, 2.0, 0.5, on());
+        assert_eq!(display.get_at(1, 0), Pixel::default().blend(on(), 128));
+        assert_eq!(display.get_at(1, 1), Pixel::default().blend(on(), 128));
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let mut display = MemoryDisplay::new(5, 5);
+        display.rect(1, 1, 3, 3, on());
+        display.flood_fill(0, 0, Pixel::rgb(0, 0, 255));
+        assert_eq!(display.get_at(2, 2), Pixel::default());
+        assert_eq!(display.get_at(4, 4), Pixel::rgb(0, 0, 255));
+        assert_eq!(display.get_at(1, 1), on());
+    }
+
+    #[test]
+    fn test_gradients_and_sprites() {
+        let mut display = MemoryDisplay::new(3, 1);
+        display.gradient((0.0, 0.0), (2.0, 0.0), Pixel::rgb(0, 0, 0), Pixel::rgb(200, 0, 0));
+        assert_eq!(display.frame(), &[Pixel::rgb(0, 0, 0), Pixel::rgb(100, 0, 0), Pixel::rgb(200, 0, 0)]);
+
+        let red = Pixel::rgb(255, 0, 0);
+        let sprite = Sprite::from_mask(2, 1, &[Some(red), None]);
+        display.fill(Pixel::rgb(0, 0, 255));
+        display.blit(&sprite, 2, 0);
+        display.blit(&sprite, 0, 0);
+        assert_eq!(display.frame(), &[red, Pixel::rgb(0, 0, 255), red]);
+
+        let sprite = Sprite::new(1, 1, vec![red]).with_alpha(vec![128]);
+        display.blit(&sprite, 1, 0);
+        assert_eq!(display.get_at(1, 0), Pixel::rgb(0, 0, 255).blend(red, 128));
+    }
+}
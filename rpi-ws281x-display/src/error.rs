@@ -0,0 +1,32 @@
+use std::{error, fmt, result};
+
+/// Errors coming from whatever is behind a display: the LED driver, a
+/// window, a socket and so on.
+#[derive(Debug)]
+pub enum Error {
+    Backend(Box<dyn error::Error + Send + Sync>),
+}
+
+impl Error {
+    pub fn backend<E: Into<Box<dyn error::Error + Send + Sync>>>(error: E) -> Self {
+        Error::Backend(error.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Backend(ref e) => write!(f, "display error: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Backend(ref e) => Some(&**e),
+        }
+    }
+}
+
+pub type Result<T> = result::Result<T, Error>;
@@ -4,79 +4,156 @@ extern crate cgmath;
 extern crate image;
 extern crate rusttype;
 extern crate imageproc;
+extern crate serde;
+extern crate gif;
+extern crate png;
+
+use std::slice::{Chunks, ChunksMut};
 
 pub mod animations;
+pub mod recorder;
+mod canvas;
+mod error;
+mod memory;
+mod pixel;
+mod raster;
+
+pub use canvas::{Canvas, Sprite};
+pub use error::{Error, Result};
+pub use memory::MemoryDisplay;
+pub use pixel::{Pixel, ParsePixelError};
+pub use raster::{blit, AlphaMode, BlitOptions, Filter, Orientation, Raster};
+
+/// The font the animations write with, for anything showing text next to
+/// them.
+pub const FONT: &[u8] = include_bytes!("animations/font.ttf");
 
-pub trait Animation<T: PixelDisplay> {
+/// Animations run on whichever thread drives the display, so they have to
+/// be `Send`. They are usually written for any display, which makes
+/// `Box<dyn Animation>` work with every backend.
+pub trait Animation<T: PixelDisplay + ?Sized = dyn PixelDisplay>: Send {
     fn setup(&mut self, display: &mut T);
     fn update(&mut self, display: &mut T, delta: f64, elapsed: f64);
     fn is_finished(&self, display: &mut T, elapsed: f64) -> bool;
+
+    /// A short name for the animation, used to show and pick it from a
+    /// playlist. Defaults to the name of the type.
+    fn name(&self) -> &str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
 }
 
+/// A grid of pixels. This can be used as `dyn PixelDisplay` so that the
+/// backend can be picked at runtime; `PixelDisplayExt` has the generic
+/// conveniences.
 pub trait PixelDisplay {
     fn rows(&self) -> usize;
     fn cols(&self) -> usize;
-    fn set_at<T: Into<usize>>(&mut self, x: T, y: T, pixel: Pixel);
-    fn get_at<T: Into<usize>>(&self, x: T, y: T) -> Pixel;
-    fn render(&mut self);
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel);
+    fn get_at(&self, x: usize, y: usize) -> Pixel;
+    /// Every pixel, row by row.
+    fn frame(&self) -> &[Pixel];
+    /// Every pixel, row by row, to change several at once. Changes show
+    /// up on the next render, the same as with `set_at`.
+    fn frame_mut(&mut self) -> &mut [Pixel];
+    /// Shows everything that was set since the last render.
+    fn render(&mut self) -> Result<()>;
     fn clear(&mut self);
     fn get_brightness(&self) -> u8;
     fn set_brightness(&mut self, brightness: u8);
-}
 
-#[derive(Debug, Copy, Clone)]
-pub struct Pixel(u32);
+    /// Sets every pixel to `pixel`.
+    fn fill(&mut self, pixel: Pixel) {
+        for p in self.frame_mut() {
+            *p = pixel;
+        }
+    }
 
-impl Default for Pixel {
-    fn default() -> Self {
-        Self::new(0u32, 0u32, 0u32, 0u32)
+    /// Sets the display from a whole frame, row by row. Pixels past the
+    /// end of the display are ignored and a short frame leaves the rest
+    /// as it was.
+    fn set_frame(&mut self, frame: &[Pixel]) {
+        let buffer = self.frame_mut();
+        let len = buffer.len().min(frame.len());
+        buffer[..len].copy_from_slice(&frame[..len]);
+    }
+
+    /// The rows of the display, top to bottom.
+    fn pixels(&self) -> Chunks<'_, Pixel> {
+        let cols = self.cols().max(1);
+        self.frame().chunks(cols)
     }
-}
 
-impl From<Pixel> for u32 {
-    fn from(pixel: Pixel) -> Self {
-        pixel.0
+    /// The rows of the display, top to bottom, to change in place.
+    fn pixels_mut(&mut self) -> ChunksMut<'_, Pixel> {
+        let cols = self.cols().max(1);
+        self.frame_mut().chunks_mut(cols)
     }
 }
 
-impl From<u32> for Pixel {
-    fn from(val: u32) -> Self {
-        Pixel(val)
+/// Helpers for every `PixelDisplay` that can't be part of the trait itself
+/// without making it unusable as a trait object.
+pub trait PixelDisplayExt: PixelDisplay {
+    /// Like `set_at`, for any unsigned coordinates.
+    fn set<T: Into<usize>>(&mut self, x: T, y: T, pixel: Pixel) {
+        self.set_at(x.into(), y.into(), pixel);
+    }
+
+    /// Like `get_at`, for any unsigned coordinates.
+    fn get<T: Into<usize>>(&self, x: T, y: T) -> Pixel {
+        self.get_at(x.into(), y.into())
     }
 }
 
-impl Pixel {
-    pub fn new<T: Into<u32>>(r: T, g: T, b: T, w: T) -> Pixel {
-        Pixel(w.into() << 24 | r.into() << 16 | g.into() << 8 | b.into())
+impl<D: PixelDisplay + ?Sized> PixelDisplayExt for D {}
+
+impl<D: PixelDisplay + ?Sized> PixelDisplay for Box<D> {
+    fn rows(&self) -> usize {
+        (**self).rows()
+    }
+
+    fn cols(&self) -> usize {
+        (**self).cols()
+    }
+
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
+        (**self).set_at(x, y, pixel)
+    }
+
+    fn get_at(&self, x: usize, y: usize) -> Pixel {
+        (**self).get_at(x, y)
+    }
+
+    fn frame(&self) -> &[Pixel] {
+        (**self).frame()
+    }
+
+    fn frame_mut(&mut self) -> &mut [Pixel] {
+        (**self).frame_mut()
     }
 
-    pub fn w(self) -> u8 {
-        ((u32::from(self) & 0xff000000) >> 24) as u8
+    fn render(&mut self) -> Result<()> {
+        (**self).render()
     }
 
-    pub fn r(self) -> u8 {
-        ((u32::from(self) & 0x00ff0000) >> 16) as u8
+    fn clear(&mut self) {
+        (**self).clear()
     }
 
-    pub fn g(self) -> u8 {
-        ((u32::from(self) & 0x0000ff00) >> 8) as u8
+    fn get_brightness(&self) -> u8 {
+        (**self).get_brightness()
     }
 
-    pub fn b(self) -> u8 {
-        (u32::from(self) & 0x0000ff) as u8
+    fn set_brightness(&mut self, brightness: u8) {
+        (**self).set_brightness(brightness)
     }
 
-    pub fn at_brightness(&self, brightness: u8) -> Pixel {
-        let scalar = brightness as f64 / 255.0;
-        self.scale(scalar)
+    fn fill(&mut self, pixel: Pixel) {
+        (**self).fill(pixel)
     }
 
-    pub fn scale(&self, scalar: f64) -> Pixel {
-        Pixel::new(
-            (self.r() as f64 * scalar) as u8,
-            (self.g() as f64 * scalar) as u8,
-            (self.b() as f64 * scalar) as u8,
-            (self.w() as f64 * scalar) as u8,
-        )
+    fn set_frame(&mut self, frame: &[Pixel]) {
+        (**self).set_frame(frame)
     }
 }
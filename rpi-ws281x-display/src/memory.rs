@@ -0,0 +1,105 @@
+use ::{Pixel, PixelDisplay, Result};
+
+/// A display that only exists in memory. Useful for rendering
+/// animations on machines without LEDs or a window.
+#[derive(Clone, Debug)]
+pub struct MemoryDisplay {
+    rows: usize,
+    cols: usize,
+    brightness: u8,
+    buffer: Vec<Pixel>,
+    renders: usize,
+}
+
+impl MemoryDisplay {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        MemoryDisplay {
+            rows,
+            cols,
+            brightness: 255,
+            buffer: vec![Pixel::default(); rows * cols],
+            renders: 0,
+        }
+    }
+
+    /// The current contents of the display in row-major order.
+    pub fn buffer(&self) -> &[Pixel] {
+        &self.buffer
+    }
+
+    /// The number of times `render` has been called.
+    pub fn renders(&self) -> usize {
+        self.renders
+    }
+}
+
+impl PixelDisplay for MemoryDisplay {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.cols
+    }
+
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
+        if x < self.cols && y < self.rows {
+            self.buffer[x + y * self.cols] = pixel;
+        }
+    }
+
+    fn get_at(&self, x: usize, y: usize) -> Pixel {
+        self.buffer[x + y * self.cols]
+    }
+
+    fn frame(&self) -> &[Pixel] {
+        &self.buffer
+    }
+
+    fn frame_mut(&mut self) -> &mut [Pixel] {
+        &mut self.buffer
+    }
+
+    fn render(&mut self) -> Result<()> {
+        self.renders += 1;
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.fill(Pixel::default());
+    }
+
+    fn get_brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bulk_access() {
+        let mut display = MemoryDisplay::new(3, 2);
+        let red = Pixel::rgb(255, 0, 0);
+        display.fill(red);
+        assert!(display.frame().iter().all(|p| *p == red));
+
+        display.set_frame(&[Pixel::rgb(0, 0, 1), Pixel::rgb(0, 0, 2), Pixel::rgb(0, 0, 3), Pixel::rgb(0, 0, 4)]);
+        assert_eq!(display.get_at(0, 1), Pixel::rgb(0, 0, 4));
+        assert_eq!(display.get_at(1, 1), red);
+
+        {
+            let display: &mut dyn PixelDisplay = &mut display;
+            for (y, row) in display.pixels_mut().enumerate() {
+                row[2] = Pixel::rgb(0, y as u8, 0);
+            }
+            assert_eq!(display.pixels().count(), 2);
+        }
+        assert_eq!(display.get_at(2, 1), Pixel::rgb(0, 1, 0));
+    }
+}
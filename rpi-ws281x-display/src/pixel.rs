@@ -0,0 +1,275 @@
+use std::{error, fmt};
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, SeqAccess, Visitor};
+
+/// A single RGBW color packed as `0xWWRRGGBB`.
+///
+/// All constructors saturate their channels at 255 so that an out of
+/// range value can never bleed into its neighbor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Pixel(u32);
+
+impl Default for Pixel {
+    fn default() -> Self {
+        Self::new(0u32, 0u32, 0u32, 0u32)
+    }
+}
+
+impl From<Pixel> for u32 {
+    fn from(pixel: Pixel) -> Self {
+        pixel.0
+    }
+}
+
+impl From<u32> for Pixel {
+    fn from(val: u32) -> Self {
+        Pixel(val)
+    }
+}
+
+fn saturate<T: Into<u32>>(channel: T) -> u32 {
+    channel.into().min(0xff)
+}
+
+fn saturate_f32(channel: f32) -> u8 {
+    channel.round().clamp(0.0, 255.0) as u8
+}
+
+impl Pixel {
+    /// Creates a new pixel, clamping every channel to 255.
+    pub fn new<T: Into<u32>>(r: T, g: T, b: T, w: T) -> Pixel {
+        Pixel(saturate(w) << 24 | saturate(r) << 16 | saturate(g) << 8 | saturate(b))
+    }
+
+    /// Creates a new pixel with the white channel turned off.
+    pub fn rgb(r: u8, g: u8, b: u8) -> Pixel {
+        Pixel::new(r, g, b, 0)
+    }
+
+    /// Creates a new pixel from floating point channels in the range
+    /// `0.0..=1.0`. Values outside of that range are clamped.
+    pub fn from_f32(r: f32, g: f32, b: f32, w: f32) -> Pixel {
+        Pixel::new(
+            saturate_f32(r * 255.0),
+            saturate_f32(g * 255.0),
+            saturate_f32(b * 255.0),
+            saturate_f32(w * 255.0),
+        )
+    }
+
+    pub fn w(self) -> u8 {
+        ((u32::from(self) & 0xff000000) >> 24) as u8
+    }
+
+    pub fn r(self) -> u8 {
+        ((u32::from(self) & 0x00ff0000) >> 16) as u8
+    }
+
+    pub fn g(self) -> u8 {
+        ((u32::from(self) & 0x0000ff00) >> 8) as u8
+    }
+
+    pub fn b(self) -> u8 {
+        (u32::from(self) & 0x0000ff) as u8
+    }
+
+    pub fn at_brightness(&self, brightness: u8) -> Pixel {
+        let scalar = brightness as f64 / 255.0;
+        self.scale(scalar)
+    }
+
+    pub fn scale(&self, scalar: f64) -> Pixel {
+        Pixel::new(
+            (self.r() as f64 * scalar) as u8,
+            (self.g() as f64 * scalar) as u8,
+            (self.b() as f64 * scalar) as u8,
+            (self.w() as f64 * scalar) as u8,
+        )
+    }
+
+    /// Linearly interpolates between this pixel and `other`. A `t` of
+    /// `0.0` returns `self` and `1.0` returns `other`.
+    pub fn lerp(self, other: Pixel, t: f32) -> Pixel {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| saturate_f32(a as f32 + (b as f32 - a as f32) * t);
+        Pixel::new(
+            mix(self.r(), other.r()),
+            mix(self.g(), other.g()),
+            mix(self.b(), other.b()),
+            mix(self.w(), other.w()),
+        )
+    }
+
+    /// Draws `over` on top of this pixel with the given opacity, where
+    /// 0 leaves this pixel untouched and 255 replaces it entirely.
+    pub fn blend(self, over: Pixel, alpha: u8) -> Pixel {
+        self.lerp(over, alpha as f32 / 255.0)
+    }
+}
+
+impl Add for Pixel {
+    type Output = Pixel;
+
+    /// Adds each channel, saturating at 255.
+    fn add(self, other: Pixel) -> Pixel {
+        Pixel::new(
+            self.r().saturating_add(other.r()),
+            self.g().saturating_add(other.g()),
+            self.b().saturating_add(other.b()),
+            self.w().saturating_add(other.w()),
+        )
+    }
+}
+
+impl Sub for Pixel {
+    type Output = Pixel;
+
+    /// Subtracts each channel, saturating at 0.
+    fn sub(self, other: Pixel) -> Pixel {
+        Pixel::new(
+            self.r().saturating_sub(other.r()),
+            self.g().saturating_sub(other.g()),
+            self.b().saturating_sub(other.b()),
+            self.w().saturating_sub(other.w()),
+        )
+    }
+}
+
+impl Mul<f32> for Pixel {
+    type Output = Pixel;
+
+    /// Scales each channel, clamping the result to `0..=255`.
+    fn mul(self, scalar: f32) -> Pixel {
+        Pixel::new(
+            saturate_f32(self.r() as f32 * scalar),
+            saturate_f32(self.g() as f32 * scalar),
+            saturate_f32(self.b() as f32 * scalar),
+            saturate_f32(self.w() as f32 * scalar),
+        )
+    }
+}
+
+/// Formats the pixel as `#rrggbb`, or `#rrggbbww` when the white
+/// channel is in use.
+impl fmt::Display for Pixel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r(), self.g(), self.b())?;
+        if self.w() != 0 {
+            write!(f, "{:02x}", self.w())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePixelError(String);
+
+impl fmt::Display for ParsePixelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid color {:?}, expected #RRGGBB or #RRGGBBWW", self.0)
+    }
+}
+
+impl error::Error for ParsePixelError {
+    fn description(&self) -> &str {
+        "invalid color"
+    }
+}
+
+/// Parses `#RRGGBB` or `#RRGGBBWW`; the leading `#` is optional.
+impl FromStr for Pixel {
+    type Err = ParsePixelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParsePixelError(s.to_string());
+        let hex = s.trim();
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(err());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| err());
+        let w = if hex.len() == 8 { channel(6)? } else { 0 };
+        Ok(Pixel::new(channel(0)?, channel(2)?, channel(4)?, w))
+    }
+}
+
+impl Serialize for Pixel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+struct PixelVisitor;
+
+impl<'de> Visitor<'de> for PixelVisitor {
+    type Value = Pixel;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a \"#RRGGBB[WW]\" string or an [r, g, b(, w)] array")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Pixel, E> {
+        value.parse().map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Pixel, A::Error> {
+        let mut channels = [0u8; 4];
+        let mut len = 0;
+        while let Some(channel) = seq.next_element::<u8>()? {
+            if len == channels.len() {
+                return Err(de::Error::invalid_length(len + 1, &self));
+            }
+            channels[len] = channel;
+            len += 1;
+        }
+        if len < 3 {
+            return Err(de::Error::invalid_length(len, &self));
+        }
+        Ok(Pixel::new(channels[0], channels[1], channels[2], channels[3]))
+    }
+}
+
+impl<'de> Deserialize<'de> for Pixel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Pixel, D::Error> {
+        deserializer.deserialize_any(PixelVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pixel;
+
+    #[test]
+    fn test_new_saturates() {
+        let p = Pixel::new(300u32, 0, 1000, 255);
+        assert_eq!((p.r(), p.g(), p.b(), p.w()), (255, 0, 255, 255));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Pixel::new(200u8, 10, 0, 0);
+        let b = Pixel::new(100u8, 20, 0, 0);
+        assert_eq!(a + b, Pixel::new(255u8, 30, 0, 0));
+        assert_eq!(a - b, Pixel::new(100u8, 0, 0, 0));
+        assert_eq!(a * 2.0, Pixel::new(255u8, 20, 0, 0));
+        assert_eq!(a.lerp(b, 0.5), Pixel::new(150u8, 15, 0, 0));
+        assert_eq!(a.blend(b, 255), b);
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let p: Pixel = "#ff8000".parse().unwrap();
+        assert_eq!(p, Pixel::rgb(255, 128, 0));
+        assert_eq!(p.to_string(), "#ff8000");
+
+        let p: Pixel = "FF800040".parse().unwrap();
+        assert_eq!(p, Pixel::new(255u8, 128, 0, 64));
+        assert_eq!(p.to_string(), "#ff800040");
+
+        assert!("#ff80".parse::<Pixel>().is_err());
+        assert!("#gg8000".parse::<Pixel>().is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+use ::{Pixel, PixelDisplay};
+
+/// How an image is shrunk or stretched to the size of the display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// Takes the closest pixel, for crisp pixel art.
+    Nearest,
+    /// Averages every image pixel that falls on an LED, weighted by how
+    /// opaque it is. The best choice for shrinking a supersampled image.
+    Area,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl Filter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            Filter::Nearest | Filter::Area => FilterType::Nearest,
+            Filter::Triangle => FilterType::Triangle,
+            Filter::CatmullRom => FilterType::CatmullRom,
+            Filter::Gaussian => FilterType::Gaussian,
+            Filter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Which way up an image goes on the display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    Normal,
+    /// Turned a quarter clockwise.
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+    /// The image's rows become the display's columns, as if mirrored along
+    /// the diagonal from the top left.
+    Transpose,
+}
+
+impl Orientation {
+    /// Whether the image is on its side, so that its width runs along the
+    /// display's rows.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Orientation::Rotate90 | Orientation::Rotate270 | Orientation::Transpose)
+    }
+
+    fn apply(self, image: &RgbaImage) -> Option<RgbaImage> {
+        Some(match self {
+            Orientation::Normal => return None,
+            Orientation::Rotate90 => imageops::rotate90(image),
+            Orientation::Rotate180 => imageops::rotate180(image),
+            Orientation::Rotate270 => imageops::rotate270(image),
+            Orientation::FlipHorizontal => imageops::flip_horizontal(image),
+            Orientation::FlipVertical => imageops::flip_vertical(image),
+            Orientation::Transpose => {
+                let (width, height) = image.dimensions();
+                RgbaImage::from_fn(height, width, |x, y| *image.get_pixel(y, x))
+            }
+        })
+    }
+}
+
+/// What to do with see-through parts of an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// Draws the image over what is already on the display.
+    Blend,
+    /// Ignores the alpha channel and replaces every pixel.
+    Opaque,
+}
+
+/// How `blit` puts an image on a display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlitOptions {
+    pub filter: Filter,
+    pub orientation: Orientation,
+    pub alpha: AlphaMode,
+    /// The white channel for every pixel drawn, since images don't have
+    /// one.
+    pub white: u8,
+}
+
+impl Default for BlitOptions {
+    fn default() -> Self {
+        BlitOptions {
+            filter: Filter::Area,
+            orientation: Orientation::Normal,
+            alpha: AlphaMode::Blend,
+            white: 0,
+        }
+    }
+}
+
+/// Draws `image` on the whole display, scaled to fit it exactly.
+pub fn blit<D: PixelDisplay + ?Sized>(display: &mut D, image: &RgbaImage, options: &BlitOptions) {
+    let oriented = options.orientation.apply(image);
+    let image = oriented.as_ref().unwrap_or(image);
+    let (cols, rows) = (display.cols() as u32, display.rows() as u32);
+    if cols == 0 || rows == 0 || image.width() == 0 || image.height() == 0 {
+        return;
+    }
+
+    let resized;
+    let image = if image.dimensions() == (cols, rows) {
+        image
+    } else {
+        resized = resize(image, cols, rows, options.filter);
+        &resized
+    };
+
+    for (x, y, p) in image.enumerate_pixels() {
+        let pixel = Pixel::new(p[0], p[1], p[2], options.white);
+        let (x, y) = (x as usize, y as usize);
+        match options.alpha {
+            AlphaMode::Opaque => display.set_at(x, y, pixel),
+            AlphaMode::Blend => {
+                let under = display.get_at(x, y);
+                display.set_at(x, y, under.blend(pixel, p[3]));
+            }
+        }
+    }
+}
+
+/// Scales `image` to exactly `width` by `height`.
+pub(crate) fn resize(image: &RgbaImage, width: u32, height: u32, filter: Filter) -> RgbaImage {
+    match filter {
+        Filter::Area => area_resize(image, width, height),
+        filter => imageops::resize(image, width, height, filter.filter_type()),
+    }
+}
+
+/// Averages the block of pixels under every output pixel, premultiplying
+/// by alpha so that transparent pixels don't darken the edges of shapes.
+fn area_resize(image: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+    let (src_width, src_height) = image.dimensions();
+    let span = |i: u32, src: u32, dst: u32| {
+        let start = (u64::from(i) * u64::from(src) / u64::from(dst)) as u32;
+        let end = (u64::from(i + 1) * u64::from(src) / u64::from(dst)) as u32;
+        (start, end.max(start + 1))
+    };
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (x0, x1) = span(x, src_width, width);
+        let (y0, y1) = span(y, src_height, height);
+        let mut sum = [0u64; 4];
+        for sy in y0..y1 {
+            for sx in x0..x1 {
+                let p = image.get_pixel(sx, sy);
+                let alpha = u64::from(p[3]);
+                for c in 0..3 {
+                    sum[c] += u64::from(p[c]) * alpha;
+                }
+                sum[3] += alpha;
+            }
+        }
+        let count = u64::from((x1 - x0) * (y1 - y0));
+        if sum[3] == 0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let channel = |c: usize| ((sum[c] + sum[3] / 2) / sum[3]) as u8;
+        Rgba([channel(0), channel(1), channel(2), ((sum[3] + count / 2) / count) as u8])
+    })
+}
+
+/// An image to draw on with `imageproc`, or anything else that works on
+/// an `RgbaImage`, before showing it on a display. It can be several
+/// times the size of the display, which smooths edges and text once it
+/// is scaled back down.
+///
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # extern crate rpi_ws281x_display;
+/// use image::Rgba;
+/// use imageproc::drawing::draw_filled_circle_mut;
+/// use rpi_ws281x_display::{MemoryDisplay, Raster};
+///
+/// # fn main() {
+/// let mut display = MemoryDisplay::new(8, 8);
+/// let mut raster = Raster::new(&display, 4);
+/// draw_filled_circle_mut(raster.image_mut(), (16, 16), 12, Rgba([255, 0, 0, 255]));
+/// raster.blit(&mut display);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Raster {
+    image: RgbaImage,
+    options: BlitOptions,
+}
+
+impl Raster {
+    /// A transparent image `scale` times the size of the display.
+    pub fn new<D: PixelDisplay + ?Sized>(display: &D, scale: u32) -> Self {
+        Raster::with_options(display, scale, BlitOptions::default())
+    }
+
+    /// Like `new`, turning the image around if the orientation puts it on
+    /// its side so that it still covers the display.
+    pub fn with_options<D: PixelDisplay + ?Sized>(display: &D, scale: u32, options: BlitOptions) -> Self {
+        let scale = scale.max(1);
+        let (mut width, mut height) = (display.cols() as u32 * scale, display.rows() as u32 * scale);
+        if options.orientation.is_sideways() {
+            ::std::mem::swap(&mut width, &mut height);
+        }
+        Raster { image: RgbaImage::new(width, height), options }
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn image_mut(&mut self) -> &mut RgbaImage {
+        &mut self.image
+    }
+
+    pub fn options(&self) -> &BlitOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut BlitOptions {
+        &mut self.options
+    }
+
+    /// Makes the whole image transparent again.
+    pub fn clear(&mut self) {
+        for p in self.image.pixels_mut() {
+            *p = Rgba([0, 0, 0, 0]);
+        }
+    }
+
+    /// Draws the image on the display, see `blit`.
+    pub fn blit<D: PixelDisplay + ?Sized>(&self, display: &mut D) {
+        blit(display, &self.image, &self.options);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::MemoryDisplay;
+
+    fn opaque(r: u8, g: u8, b: u8) -> Rgba<u8> {
+        Rgba([r, g, b, 255])
+    }
+
+    #[test]
+    fn test_area_filter_averages_blocks() {
+        let mut display = MemoryDisplay::new(2, 1);
+        let mut raster = Raster::new(&display, 2);
+        assert_eq!(raster.image().dimensions(), (4, 2));
+        // the left LED is half white, the right one has a single red pixel
+        raster.image_mut().put_pixel(0, 0, opaque(255, 255, 255));
+        raster.image_mut().put_pixel(1, 1, opaque(255, 255, 255));
+        raster.image_mut().put_pixel(3, 0, opaque(255, 0, 0));
+        raster.options_mut().alpha = AlphaMode::Opaque;
+        raster.blit(&mut display);
+
+        // transparent pixels don't darken the color, only its coverage
+        assert_eq!(display.get_at(0, 0), Pixel::rgb(255, 255, 255));
+        assert_eq!(display.get_at(1, 0), Pixel::rgb(255, 0, 0));
+
+        let mut display = MemoryDisplay::new(2, 1);
+        raster.options_mut().alpha = AlphaMode::Blend;
+        raster.blit(&mut display);
+        assert_eq!(display.get_at(0, 0), Pixel::rgb(128, 128, 128));
+        assert_eq!(display.get_at(1, 0), Pixel::rgb(64, 0, 0));
+    }
+
+    #[test]
+    fn test_orientation() {
+        let image = RgbaImage::from_fn(3, 2, |x, y| opaque(x as u8, y as u8, 0));
+        let at = |display: &MemoryDisplay, x, y| {
+            let p = display.get_at(x, y);
+            (p.r(), p.g())
+        };
+
+        let mut display = MemoryDisplay::new(2, 3);
+        let mut options = BlitOptions { orientation: Orientation::Transpose, ..Default::default() };
+        blit(&mut display, &image, &options);
+        assert_eq!(at(&display, 1, 2), (2, 1));
+
+        options.orientation = Orientation::Rotate90;
+        blit(&mut display, &image, &options);
+        assert_eq!(at(&display, 0, 0), (0, 1));
+        assert_eq!(at(&display, 1, 2), (2, 0));
+
+        let mut display = MemoryDisplay::new(3, 2);
+        options.orientation = Orientation::FlipHorizontal;
+        blit(&mut display, &image, &options);
+        assert_eq!(at(&display, 0, 1), (2, 1));
+    }
+
+    #[test]
+    fn test_scales_to_display() {
+        let image = RgbaImage::from_pixel(1, 1, opaque(0, 0, 255));
+        let mut display = MemoryDisplay::new(3, 2);
+        let options = BlitOptions { filter: Filter::Nearest, white: 7, ..Default::default() };
+        blit(&mut display, &image, &options);
+        assert!(display.frame().iter().all(|p| *p == Pixel::new(0u8, 0, 255, 7)));
+
+        let sideways = Raster::with_options(&display, 2, BlitOptions { orientation: Orientation::Rotate270, ..options });
+        assert_eq!(sideways.image().dimensions(), (4, 6));
+    }
+}
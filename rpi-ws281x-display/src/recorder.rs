@@ -0,0 +1,381 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use gif::{self, SetParameter};
+use image::{Rgb, RgbImage};
+use png;
+
+use ::{Pixel, PixelDisplay, Result};
+
+/// The file format produced by `Recorder::encode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    Gif,
+    Apng,
+}
+
+impl RecordFormat {
+    /// Guesses the format from a file extension, e.g. `preview.gif`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<RecordFormat> {
+        let ext = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "gif" => Some(RecordFormat::Gif),
+            "png" | "apng" => Some(RecordFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// How a single LED is drawn into a recorded frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DotStyle {
+    /// Fill the whole dot with a flat square.
+    Square,
+    /// An anti-aliased circle.
+    Circle,
+    /// A circle whose light bleeds into the spacing around it, which is
+    /// the closest thing to the simulator's ellipses.
+    Glow,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecorderOptions {
+    /// The width and height in image pixels of a single LED.
+    pub dot_size: u32,
+    /// The gap in image pixels between neighboring LEDs.
+    pub spacing: u32,
+    pub style: DotStyle,
+    /// The playback rate of the recording.
+    pub fps: u32,
+}
+
+impl Default for RecorderOptions {
+    fn default() -> Self {
+        RecorderOptions {
+            dot_size: 16,
+            spacing: 4,
+            style: DotStyle::Glow,
+            fps: 30,
+        }
+    }
+}
+
+/// A `PixelDisplay` wrapper that keeps a copy of every rendered frame
+/// so that it can be written out as an animated GIF or APNG.
+///
+/// Frames are captured at the wrapped display's brightness. Only the
+/// RGB channels are drawn; the white channel is not previewed.
+///
+/// ```ignore
+/// let mut lightbox = LightBox::new(Recorder::new(display, RecorderOptions::default()), playlist, 30);
+/// // ... run the lightbox ...
+/// lightbox.display.save("preview.gif")?;
+/// ```
+pub struct Recorder<D: PixelDisplay> {
+    display: D,
+    options: RecorderOptions,
+    frames: Vec<Vec<Pixel>>,
+}
+
+impl<D: PixelDisplay> Recorder<D> {
+    pub fn new(display: D, options: RecorderOptions) -> Self {
+        Recorder {
+            display,
+            options,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn options(&self) -> &RecorderOptions {
+        &self.options
+    }
+
+    /// The frames captured so far, each one in row-major order.
+    pub fn frames(&self) -> &[Vec<Pixel>] {
+        &self.frames
+    }
+
+    /// Throws away every captured frame.
+    pub fn clear_frames(&mut self) {
+        self.frames.clear();
+    }
+
+    pub fn into_inner(self) -> D {
+        self.display
+    }
+
+    /// The size in image pixels of a rendered frame.
+    pub fn frame_size(&self) -> (u32, u32) {
+        let cell = self.options.dot_size + self.options.spacing;
+        (
+            self.display.cols() as u32 * cell + self.options.spacing,
+            self.display.rows() as u32 * cell + self.options.spacing,
+        )
+    }
+
+    /// Draws the captured frame at `index` as an image.
+    pub fn render_frame(&self, index: usize) -> RgbImage {
+        let (width, height) = self.frame_size();
+        let (rows, cols) = (self.display.rows(), self.display.cols());
+        if rows == 0 || cols == 0 {
+            // nothing but the spacing around no LEDs at all
+            return RgbImage::new(width, height);
+        }
+        let frame = &self.frames[index];
+        let dot = self.options.dot_size as f32;
+        let spacing = self.options.spacing as f32;
+        let cell = dot + spacing;
+        let radius = dot / 2.0;
+
+        RgbImage::from_fn(width, height, |px, py| {
+            // every image pixel is lit by the LED whose cell it falls into
+            let fx = px as f32 + 0.5 - spacing / 2.0;
+            let fy = py as f32 + 0.5 - spacing / 2.0;
+            let col = ((fx / cell).floor().max(0.0) as usize).min(cols - 1);
+            let row = ((fy / cell).floor().max(0.0) as usize).min(rows - 1);
+            let center_x = spacing + col as f32 * cell + radius;
+            let center_y = spacing + row as f32 * cell + radius;
+            let (dx, dy) = (px as f32 + 0.5 - center_x, py as f32 + 0.5 - center_y);
+
+            let intensity = match self.options.style {
+                DotStyle::Square => {
+                    if dx.abs() <= radius && dy.abs() <= radius { 1.0 } else { 0.0 }
+                }
+                DotStyle::Circle => {
+                    (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0)
+                }
+                DotStyle::Glow => {
+                    let d = (dx * dx + dy * dy).sqrt();
+                    let core = (radius * 0.8 - d + 0.5).clamp(0.0, 1.0);
+                    let halo = (1.0 - d / (radius + spacing / 2.0)).max(0.0);
+                    core.max(halo * halo * 0.6)
+                }
+            };
+            let p = frame[col + row * cols] * intensity;
+            Rgb([p.r(), p.g(), p.b()])
+        })
+    }
+
+    /// Writes every captured frame to `path`, picking the format from
+    /// the file extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let format = RecordFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "expected a .gif or .png file extension")
+        })?;
+        let file = BufWriter::new(File::create(path)?);
+        self.encode(file, format)
+    }
+
+    /// Writes every captured frame as an animation that loops forever.
+    pub fn encode<W: Write>(&self, w: W, format: RecordFormat) -> io::Result<()> {
+        if self.frames.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no frames were recorded"));
+        }
+        match format {
+            RecordFormat::Gif => self.encode_gif(w),
+            RecordFormat::Apng => self.encode_apng(w),
+        }
+    }
+
+    fn encode_gif<W: Write>(&self, w: W) -> io::Result<()> {
+        let (width, height) = self.frame_size();
+        if width > u32::from(u16::MAX) || height > u32::from(u16::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}x{} is too large for a GIF, which is at most 65535x65535", width, height),
+            ));
+        }
+        let (width, height) = (width as u16, height as u16);
+        let mut encoder = gif::Encoder::new(w, width, height, &[])?;
+        encoder.set(gif::Repeat::Infinite)?;
+
+        // GIF delays are in hundredths of a second, so carry the rounding
+        // error over to the next frame to keep the total length right.
+        let delay = 100.0 / self.options.fps.max(1) as f64;
+        let mut carry = 0.0;
+        for index in 0..self.frames.len() {
+            let image = self.render_frame(index);
+            let mut frame = gif::Frame::from_rgb_speed(width, height, &image, 10);
+            let exact = delay + carry;
+            frame.delay = exact.round() as u16;
+            carry = exact - frame.delay as f64;
+            encoder.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+
+    fn encode_apng<W: Write>(&self, w: W) -> io::Result<()> {
+        let (width, height) = self.frame_size();
+        let mut encoder = png::Encoder::new(w, width, height);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+
+        let mut actl = Vec::with_capacity(8);
+        actl.extend_from_slice(&be_bytes(self.frames.len() as u32));
+        // 0 plays means loop forever
+        actl.extend_from_slice(&be_bytes(0));
+        writer.write_chunk(*b"acTL", &actl)?;
+
+        let fps = self.options.fps.clamp(1, u16::MAX as u32) as u16;
+        let mut sequence = 0u32;
+        for index in 0..self.frames.len() {
+            let mut fctl = Vec::with_capacity(26);
+            fctl.extend_from_slice(&be_bytes(sequence));
+            fctl.extend_from_slice(&be_bytes(width));
+            fctl.extend_from_slice(&be_bytes(height));
+            // x and y offset
+            fctl.extend_from_slice(&be_bytes(0));
+            fctl.extend_from_slice(&be_bytes(0));
+            // a delay of 1/fps seconds
+            fctl.extend_from_slice(&1u16.to_be_bytes());
+            fctl.extend_from_slice(&fps.to_be_bytes());
+            // dispose op: none, blend op: source
+            fctl.extend_from_slice(&[0, 0]);
+            writer.write_chunk(*b"fcTL", &fctl)?;
+            sequence += 1;
+
+            let image = self.render_frame(index);
+            if index == 0 {
+                // the first frame doubles as the still image for
+                // viewers that don't understand APNG
+                writer.write_image_data(&image)?;
+            } else {
+                let mut fdat = be_bytes(sequence).to_vec();
+                fdat.extend(compress_png_data(width, height, &image)?);
+                writer.write_chunk(*b"fdAT", &fdat)?;
+                sequence += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn be_bytes(val: u32) -> [u8; 4] {
+    val.to_be_bytes()
+}
+
+/// Compresses raw RGB data the same way the PNG encoder does for IDAT
+/// chunks by encoding a throwaway PNG and pulling its IDAT data back out.
+fn compress_png_data(width: u32, height: u32, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buffer, width, height);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(data)?;
+    }
+
+    // skip the 8 byte signature, then walk the length/type/data/crc chunks
+    let mut idat = Vec::new();
+    let mut pos = 8;
+    while pos + 8 <= buffer.len() {
+        let len = u32::from_be_bytes([buffer[pos], buffer[pos + 1], buffer[pos + 2], buffer[pos + 3]]) as usize;
+        let start = pos + 8;
+        if &buffer[pos + 4..start] == b"IDAT" {
+            idat.extend_from_slice(&buffer[start..start + len]);
+        }
+        pos = start + len + 4;
+    }
+    Ok(idat)
+}
+
+impl<D: PixelDisplay> PixelDisplay for Recorder<D> {
+    fn rows(&self) -> usize {
+        self.display.rows()
+    }
+
+    fn cols(&self) -> usize {
+        self.display.cols()
+    }
+
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
+        self.display.set_at(x, y, pixel);
+    }
+
+    fn get_at(&self, x: usize, y: usize) -> Pixel {
+        self.display.get_at(x, y)
+    }
+
+    fn frame(&self) -> &[Pixel] {
+        self.display.frame()
+    }
+
+    fn frame_mut(&mut self) -> &mut [Pixel] {
+        self.display.frame_mut()
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let brightness = self.display.get_brightness();
+        let frame = self.display.frame().iter().map(|p| p.at_brightness(brightness)).collect();
+        self.frames.push(frame);
+        self.display.render()
+    }
+
+    fn clear(&mut self) {
+        self.display.clear();
+    }
+
+    fn get_brightness(&self) -> u8 {
+        self.display.get_brightness()
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        self.display.set_brightness(brightness);
+    }
+
+    fn fill(&mut self, pixel: Pixel) {
+        self.display.fill(pixel);
+    }
+
+    fn set_frame(&mut self, frame: &[Pixel]) {
+        self.display.set_frame(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::MemoryDisplay;
+
+    #[test]
+    fn test_record_and_encode() {
+        let options = RecorderOptions { dot_size: 4, spacing: 2, style: DotStyle::Square, fps: 10 };
+        let mut recorder = Recorder::new(MemoryDisplay::new(2, 2), options);
+        recorder.set_at(1, 0, Pixel::rgb(255, 0, 0));
+        recorder.render().unwrap();
+        recorder.clear();
+        recorder.render().unwrap();
+        assert_eq!(recorder.frames().len(), 2);
+        assert_eq!(recorder.frame_size(), (14, 14));
+
+        let image = recorder.render_frame(0);
+        // center of the second dot in the first row vs. the gap between dots
+        assert_eq!(image.get_pixel(10, 4), &Rgb([255, 0, 0]));
+        assert_eq!(image.get_pixel(7, 4), &Rgb([0, 0, 0]));
+
+        let mut gif = Vec::new();
+        recorder.encode(&mut gif, RecordFormat::Gif).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+
+        let mut apng = Vec::new();
+        recorder.encode(&mut apng, RecordFormat::Apng).unwrap();
+        assert_eq!(&apng[1..4], b"PNG");
+        assert!(apng.windows(4).any(|w| w == b"acTL"));
+        assert!(apng.windows(4).any(|w| w == b"fdAT"));
+    }
+
+    #[test]
+    fn test_degenerate_sizes() {
+        let mut recorder = Recorder::new(MemoryDisplay::new(0, 0), RecorderOptions::default());
+        recorder.render().unwrap();
+        assert_eq!(recorder.render_frame(0).dimensions(), (4, 4));
+
+        let options = RecorderOptions { dot_size: 1000, ..RecorderOptions::default() };
+        let mut recorder = Recorder::new(MemoryDisplay::new(70, 1), options);
+        recorder.render().unwrap();
+        let err = recorder.encode(Vec::new(), RecordFormat::Gif).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
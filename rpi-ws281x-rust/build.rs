@@ -1,11 +1,25 @@
+#[cfg(feature = "native")]
 extern crate bindgen;
+#[cfg(feature = "native")]
 extern crate cc;
 
+#[cfg(feature = "native")]
 use std::env;
+#[cfg(feature = "native")]
 use std::path::PathBuf;
+#[cfg(feature = "native")]
 use std::process::Command;
 
+#[cfg(not(feature = "native"))]
+fn main() {}
+
+#[cfg(feature = "native")]
 fn main() {
+    // the mock doesn't need the C driver, even if both are enabled
+    if env::var_os("CARGO_FEATURE_MOCK").is_some() {
+        return;
+    }
+
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
 
     println!("cargo:rustc-link-search={:?}", out_path);
@@ -13,14 +13,17 @@ use super::controller::Controller;
 /// an instance of ws2811_t sitting around.
 ///
 /// ```
+/// use rs_ws281x::{ChannelBuilder, ControllerBuilder, StripType};
+///
 /// // Construct a single channel controller. Note that the
 /// // Controller is initialized by default and is cleaned up on drop
-/// let controller = ControllerBuilder::new()
+/// let mut controller = ControllerBuilder::new()
 ///     // default
 ///     .freq(800_000)
 ///     // default
 ///     .dma(10)
 ///     .channel(
+///         0,
 ///         ChannelBuilder::new()
 ///             .pin(18)
 ///             .count(10)
@@ -28,16 +31,18 @@ use super::controller::Controller;
 ///             .brightness(255)
 ///             .build()
 ///      )
-///     .build();
+///     .build()
+///     .unwrap();
 ///
 /// // get the strand of LEDs on channel 1
 /// let leds = controller.leds_mut(0);
 /// // set the first LED to white (with the configured
 /// // strip above, this is BGRW)
-/// leds[0] = [255, 255, 255, 0]
+/// leds[0] = [255, 255, 255, 0];
 ///
 /// // render it to the strand
-/// controller.render();
+/// controller.render().unwrap();
+/// ```
 #[derive(Debug)]
 pub struct ControllerBuilder(pub ws2811_t);
 
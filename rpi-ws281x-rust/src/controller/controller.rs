@@ -1,21 +1,35 @@
 use std::slice::{from_raw_parts, from_raw_parts_mut};
 
-use super::super::bindings::{ws2811_fini, ws2811_render, ws2811_t};
+use super::super::bindings::{ws2811_channel_t, ws2811_fini, ws2811_render, ws2811_t};
+#[cfg(feature = "mock")]
+use super::super::mock::MockDevice;
 use super::super::util::{Result, RawColor};
 
 /// The main struct used to control lights.  Provides ways of
 /// accessing the light color values and rendering those values to
 /// the string.
-#[derive(Clone, Debug)]
+///
+/// It owns the LED buffers the C library allocated for it and frees them
+/// on drop, which is why it can't be cloned.
+#[derive(Debug)]
 pub struct Controller {
     c_struct: ws2811_t,
 }
 
+/*
+ * The controller is the only thing pointing at the buffers and device
+ * state set up by ws2811_init(), and the C library keeps nothing tied to
+ * the thread that called it, so the whole lot can move to another thread.
+ * It isn't Sync: rendering reads the buffers that `leds_mut` hands out.
+ */
+unsafe impl Send for Controller {}
+
 impl Controller {
-    /// Creates a new Controller
+    /// Creates a new Controller from a `ws2811_t` that `ws2811_init`
+    /// succeeded on.
     ///
     /// Note: This is only to be called from the Builder struct
-    pub fn new(c_struct: ws2811_t) -> Self {
+    pub(crate) fn new(c_struct: ws2811_t) -> Self {
         Controller { c_struct }
     }
 
@@ -30,57 +44,74 @@ impl Controller {
         }
     }
 
+    /// The number of channels, whether or not they have any LEDs.
+    pub fn channel_count(&self) -> usize {
+        self.c_struct.channel.len()
+    }
+
+    /// Gets a view of one channel, or `None` if there is no such channel.
+    pub fn channel(&self, index: usize) -> Option<Channel<'_>> {
+        self.c_struct.channel.get(index).map(|c_struct| Channel { c_struct })
+    }
+
+    /// Gets a mutable view of one channel, or `None` if there is no such
+    /// channel.
+    pub fn channel_mut(&mut self, index: usize) -> Option<ChannelMut<'_>> {
+        self.c_struct.channel.get_mut(index).map(|c_struct| ChannelMut { c_struct })
+    }
+
     /// Gets a slice view to the color array to be written to the LEDs.
     /// See `leds_mut` for a mutable slice view to this data.
     ///
-    /// # Safety
-    /// This function is moderately unsafe because we rely on the promise
-    /// from the C library that it will stick to its memory layout and that
-    /// the pointer is valid.
+    /// # Panics
+    /// When there is no such channel, see `channel` for a version that
+    /// doesn't.
     pub fn leds(&self, channel: usize) -> &[RawColor] {
-        /*
-         * Using unsafe here because we want to construct a slice
-         * from just the raw pointer and the supposed number of elements
-         * which is safe as long as our friends in "C land" hold to their
-         * memory layout and we use a data type with compatible layout.
-         */
-        unsafe {
-            from_raw_parts(
-                self.c_struct.channel[channel].leds as *const RawColor,
-                self.c_struct.channel[channel].count as usize
-            )
-        }
+        self.expect_channel(channel);
+        leds(&self.c_struct.channel[channel])
     }
 
     /// Gets a mutable slice pointing to the color array to be written to
     /// the LEDs.
     ///
-    /// # Safety
-    /// This function is moderately unsafe because we rely on the promise
-    /// from the C library that it will stick to its memory layout and that
-    /// the pointer is valid.
+    /// # Panics
+    /// When there is no such channel, see `channel_mut` for a version that
+    /// doesn't.
     pub fn leds_mut(&mut self, channel: usize) -> &mut [RawColor] {
-        /*
-         * Using unsafe here because we want to construct a slice
-         * from just the raw pointer and the supposed number of elements
-         * which is safe as long as our friends in "C land" hold to their
-         * memory layout and we use a data type with compatible layout.
-         */
-        unsafe {
-            from_raw_parts_mut(
-                self.c_struct.channel[channel].leds as *mut RawColor,
-                self.c_struct.channel[channel].count as usize
-            )
-        }
+        self.expect_channel(channel);
+        leds_mut(&mut self.c_struct.channel[channel])
     }
 
+    /// # Panics
+    /// When there is no such channel.
     pub fn get_brightness(&self, channel: usize) -> u8 {
+        self.expect_channel(channel);
         self.c_struct.channel[channel].brightness
     }
 
+    /// # Panics
+    /// When there is no such channel.
     pub fn set_brightness(&mut self, channel: usize, brightness: u8) {
+        self.expect_channel(channel);
         self.c_struct.channel[channel].brightness = brightness;
     }
+
+    fn expect_channel(&self, channel: usize) {
+        if channel >= self.channel_count() {
+            panic!("channel {} out of range, the controller has {}", channel, self.channel_count());
+        }
+    }
+
+    /// The mock driver's view of the strips, see the `mock` module.
+    #[cfg(feature = "mock")]
+    pub fn device(&self) -> &MockDevice {
+        unsafe { &*self.c_struct.device }
+    }
+
+    #[cfg(feature = "mock")]
+    pub fn device_mut(&mut self) -> &mut MockDevice {
+        unsafe { &mut *self.c_struct.device }
+    }
 }
 
 impl Drop for Controller {
@@ -95,3 +126,101 @@ impl Drop for Controller {
         }
     }
 }
+
+/// A view of one of a `Controller`'s channels.
+#[derive(Debug)]
+pub struct Channel<'a> {
+    c_struct: &'a ws2811_channel_t,
+}
+
+impl<'a> Channel<'a> {
+    /// The colors that will be written to the LEDs on the next render.
+    pub fn leds(&self) -> &'a [RawColor] {
+        leds(self.c_struct)
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.c_struct.brightness
+    }
+}
+
+/// A mutable view of one of a `Controller`'s channels.
+#[derive(Debug)]
+pub struct ChannelMut<'a> {
+    c_struct: &'a mut ws2811_channel_t,
+}
+
+impl<'a> ChannelMut<'a> {
+    pub fn leds(&self) -> &[RawColor] {
+        leds(self.c_struct)
+    }
+
+    /// The colors that will be written to the LEDs on the next render.
+    pub fn leds_mut(&mut self) -> &mut [RawColor] {
+        leds_mut(self.c_struct)
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.c_struct.brightness
+    }
+
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.c_struct.brightness = brightness;
+    }
+}
+
+/*
+ * Using unsafe here because we want to construct a slice
+ * from just the raw pointer and the supposed number of elements
+ * which is safe as long as our friends in "C land" hold to their
+ * memory layout and we use a data type with compatible layout.
+ * A channel without any LEDs may not have a buffer at all.
+ */
+fn leds(channel: &ws2811_channel_t) -> &[RawColor] {
+    if channel.leds.is_null() {
+        return &[];
+    }
+    unsafe { from_raw_parts(channel.leds as *const RawColor, channel.count as usize) }
+}
+
+fn leds_mut(channel: &mut ws2811_channel_t) -> &mut [RawColor] {
+    if channel.leds.is_null() {
+        return &mut [];
+    }
+    unsafe { from_raw_parts_mut(channel.leds as *mut RawColor, channel.count as usize) }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::super::ControllerBuilder;
+    use channel::ChannelBuilder;
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[test]
+    fn test_channels() {
+        let mut controller = ControllerBuilder::new()
+            .channel(0, ChannelBuilder::new().pin(18).count(3).brightness(10).build())
+            .build()
+            .unwrap();
+        assert_send(&controller);
+        assert_eq!(controller.channel_count(), 2);
+
+        controller.channel_mut(0).unwrap().leds_mut()[2] = [1, 2, 3, 0];
+        controller.channel_mut(0).unwrap().set_brightness(20);
+        let channel = controller.channel(0).unwrap();
+        assert_eq!(channel.leds()[2], [1, 2, 3, 0]);
+        assert_eq!(channel.brightness(), 20);
+
+        assert_eq!(controller.leds(1).len(), 0);
+        assert!(controller.channel(2).is_none());
+        assert!(controller.channel_mut(2).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "channel 2 out of range")]
+    fn test_leds_out_of_range() {
+        let controller = ControllerBuilder::new().build().unwrap();
+        controller.leds(2);
+    }
+}
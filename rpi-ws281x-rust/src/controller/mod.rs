@@ -1,4 +1,4 @@
 mod builder;
 mod controller;
 pub use self::builder::ControllerBuilder;
-pub use self::controller::Controller;
+pub use self::controller::{Channel, ChannelMut, Controller};
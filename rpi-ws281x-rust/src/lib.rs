@@ -1,10 +1,13 @@
-#![feature(ptr_internals)]
+#[cfg(not(any(feature = "native", feature = "mock")))]
+compile_error!("either the \"native\" or the \"mock\" feature must be enabled");
 
 pub mod bindings;
 mod channel;
 mod controller;
+#[cfg(feature = "mock")]
+pub mod mock;
 mod util;
 
 pub use channel::{ChannelBuilder};
-pub use controller::{Controller, ControllerBuilder};
-pub use util::{StripType, RawColor};
+pub use controller::{Channel, ChannelMut, Controller, ControllerBuilder};
+pub use util::{ColorChannel, ParseStripTypeError, StripType, RawColor};
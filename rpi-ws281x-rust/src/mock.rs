@@ -0,0 +1,283 @@
+//! A pure Rust stand-in for the C driver, used instead of it when the
+//! `mock` feature is enabled so that everything built on `Controller` can
+//! be tested away from a Raspberry Pi.
+//!
+//! It mirrors the parts of `ws2811.h` this crate uses and behaves like the
+//! driver as far as the caller can tell: `ws2811_init` checks the GPIO
+//! pins and allocates the LED buffers, `ws2811_render` scales every LED by
+//! the channel brightness and orders its bytes the way the strip type
+//! says, and `ws2811_fini` frees it all again. Instead of going out to a
+//! strip, the bytes are kept on the `MockDevice` for tests to look at,
+//! which can also make renders fail.
+//!
+//! ```
+//! use rs_ws281x::{ChannelBuilder, ControllerBuilder, StripType};
+//!
+//! let mut controller = ControllerBuilder::new()
+//!     .channel(0, ChannelBuilder::new().pin(18).count(1).strip_type(StripType::Ws2811Grb).brightness(255).build())
+//!     .build()
+//!     .unwrap();
+//! controller.leds_mut(0)[0] = [0, 0, 255, 0]; // red
+//! controller.render().unwrap();
+//! assert_eq!(controller.device().wire(0), &[0, 255, 0]);
+//! ```
+
+#![allow(non_camel_case_types)]
+
+use std::cell::Cell;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+pub type ws2811_led_t = u32;
+
+pub const RPI_PWM_CHANNELS: u32 = 2;
+
+pub const SK6812_STRIP_RGBW: u32 = 0x1810_0800;
+pub const SK6812_STRIP_RBGW: u32 = 0x1810_0008;
+pub const SK6812_STRIP_GRBW: u32 = 0x1808_1000;
+pub const SK6812_STRIP_GBRW: u32 = 0x1808_0010;
+pub const SK6812_STRIP_BRGW: u32 = 0x1800_1008;
+pub const SK6812_STRIP_BGRW: u32 = 0x1800_0810;
+pub const SK6812_SHIFT_WMASK: u32 = 0xf000_0000;
+
+pub const WS2811_STRIP_RGB: u32 = 0x0010_0800;
+pub const WS2811_STRIP_RBG: u32 = 0x0010_0008;
+pub const WS2811_STRIP_GRB: u32 = 0x0008_1000;
+pub const WS2811_STRIP_GBR: u32 = 0x0008_0010;
+pub const WS2811_STRIP_BRG: u32 = 0x0000_1008;
+pub const WS2811_STRIP_BGR: u32 = 0x0000_0810;
+
+pub const WS2812_STRIP: u32 = WS2811_STRIP_GRB;
+pub const SK6812_STRIP: u32 = WS2811_STRIP_GRB;
+pub const SK6812W_STRIP: u32 = SK6812_STRIP_GRBW;
+
+/// The pins the driver can drive a strip from: PWM, PCM or SPI.
+const VALID_GPIOS: [c_int; 12] = [12, 18, 40, 52, 13, 19, 41, 45, 53, 21, 31, 10];
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ws2811_channel_t {
+    pub gpionum: c_int,
+    pub invert: c_int,
+    pub count: c_int,
+    pub strip_type: c_int,
+    pub leds: *mut ws2811_led_t,
+    pub brightness: u8,
+    pub wshift: u8,
+    pub rshift: u8,
+    pub gshift: u8,
+    pub bshift: u8,
+    pub gamma: *mut u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ws2811_t {
+    pub render_wait_time: u64,
+    pub device: *mut ws2811_device,
+    pub rpi_hw: *const c_void,
+    pub freq: u32,
+    pub dmanum: c_int,
+    pub channel: [ws2811_channel_t; RPI_PWM_CHANNELS as usize],
+}
+
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ws2811_return_t {
+    WS2811_SUCCESS = 0,
+    WS2811_ERROR_GENERIC = -1,
+    WS2811_ERROR_OUT_OF_MEMORY = -2,
+    WS2811_ERROR_HW_NOT_SUPPORTED = -3,
+    WS2811_ERROR_MEM_LOCK = -4,
+    WS2811_ERROR_MMAP = -5,
+    WS2811_ERROR_MAP_REGISTERS = -6,
+    WS2811_ERROR_GPIO_INIT = -7,
+    WS2811_ERROR_PWM_SETUP = -8,
+    WS2811_ERROR_MAILBOX_DEVICE = -9,
+    WS2811_ERROR_DMA = -10,
+    WS2811_ERROR_ILLEGAL_GPIO = -11,
+    WS2811_ERROR_PCM_SETUP = -12,
+    WS2811_ERROR_SPI_SETUP = -13,
+    WS2811_ERROR_SPI_TRANSFER = -14,
+}
+
+/// What the driver would have sent down the wire, in place of the
+/// hardware state the real `ws2811_device` holds.
+#[derive(Debug, Default)]
+pub struct ws2811_device {
+    wire: [Vec<u8>; RPI_PWM_CHANNELS as usize],
+    renders: usize,
+    failures: Vec<ws2811_return_t>,
+}
+
+pub type MockDevice = ws2811_device;
+
+impl MockDevice {
+    /// The bytes last sent to a channel, three or four per LED depending
+    /// on the strip type and in the order the strip expects them.
+    pub fn wire(&self, channel: usize) -> &[u8] {
+        &self.wire[channel]
+    }
+
+    /// How many times the strips have been rendered to successfully.
+    pub fn renders(&self) -> usize {
+        self.renders
+    }
+
+    /// Makes the next render return `error`, after any failures already
+    /// queued up.
+    pub fn fail_render(&mut self, error: ws2811_return_t) {
+        self.failures.push(error);
+    }
+}
+
+thread_local! {
+    static INIT_FAILURE: Cell<Option<ws2811_return_t>> = const { Cell::new(None) };
+}
+
+/// Makes the next `ws2811_init` on this thread return `error`.
+pub fn fail_next_init(error: ws2811_return_t) {
+    INIT_FAILURE.with(|failure| failure.set(Some(error)));
+}
+
+/// # Safety
+/// `ws2811` must point to a `ws2811_t` that isn't initialized yet.
+pub unsafe fn ws2811_init(ws2811: *mut ws2811_t) -> ws2811_return_t {
+    let ws2811 = &mut *ws2811;
+    if let Some(error) = INIT_FAILURE.with(|failure| failure.take()) {
+        return error;
+    }
+    for channel in ws2811.channel.iter() {
+        if channel.count < 0 {
+            return ws2811_return_t::WS2811_ERROR_GENERIC;
+        }
+        if channel.count > 0 && !VALID_GPIOS.contains(&channel.gpionum) {
+            return ws2811_return_t::WS2811_ERROR_ILLEGAL_GPIO;
+        }
+    }
+
+    ws2811.device = Box::into_raw(Box::new(MockDevice::default()));
+    for channel in ws2811.channel.iter_mut() {
+        let leds = vec![0 as ws2811_led_t; channel.count as usize].into_boxed_slice();
+        channel.leds = Box::into_raw(leds) as *mut ws2811_led_t;
+        if channel.gamma.is_null() {
+            let gamma: Box<[u8]> = (0..=255).collect();
+            channel.gamma = Box::into_raw(gamma) as *mut u8;
+        }
+        let strip_type = channel.strip_type as u32;
+        channel.wshift = (strip_type >> 24) as u8;
+        channel.rshift = (strip_type >> 16) as u8;
+        channel.gshift = (strip_type >> 8) as u8;
+        channel.bshift = strip_type as u8;
+    }
+    ws2811_return_t::WS2811_SUCCESS
+}
+
+/// # Safety
+/// `ws2811` must point to a `ws2811_t` set up by `ws2811_init`.
+pub unsafe fn ws2811_render(ws2811: *mut ws2811_t) -> ws2811_return_t {
+    let ws2811 = &mut *ws2811;
+    let device = match ws2811.device.as_mut() {
+        Some(device) => device,
+        None => return ws2811_return_t::WS2811_ERROR_GENERIC,
+    };
+    if !device.failures.is_empty() {
+        return device.failures.remove(0);
+    }
+
+    for (channel, wire) in ws2811.channel.iter().zip(device.wire.iter_mut()) {
+        wire.clear();
+        let scale = u32::from(channel.brightness) + 1;
+        let gamma = ::std::slice::from_raw_parts(channel.gamma, 256);
+        let shifts = [channel.rshift, channel.gshift, channel.bshift, channel.wshift];
+        let bytes = if channel.strip_type as u32 & SK6812_SHIFT_WMASK != 0 { 4 } else { 3 };
+        for &led in ::std::slice::from_raw_parts(channel.leds, channel.count as usize) {
+            for &shift in shifts[..bytes].iter() {
+                wire.push(gamma[((((led >> shift) & 0xff) * scale) >> 8) as usize]);
+            }
+        }
+    }
+    device.renders += 1;
+    ws2811_return_t::WS2811_SUCCESS
+}
+
+/// # Safety
+/// `ws2811` must point to a valid `ws2811_t`.
+pub unsafe fn ws2811_wait(ws2811: *mut ws2811_t) -> ws2811_return_t {
+    if (*ws2811).device.is_null() {
+        ws2811_return_t::WS2811_ERROR_GENERIC
+    } else {
+        ws2811_return_t::WS2811_SUCCESS
+    }
+}
+
+/// # Safety
+/// `ws2811` must point to a `ws2811_t` set up by `ws2811_init`, or one
+/// already finished with.
+pub unsafe fn ws2811_fini(ws2811: *mut ws2811_t) {
+    let ws2811 = &mut *ws2811;
+    if !ws2811.device.is_null() {
+        drop(Box::from_raw(ws2811.device));
+        ws2811.device = ptr::null_mut();
+    }
+    for channel in ws2811.channel.iter_mut() {
+        if !channel.leds.is_null() {
+            let leds = ptr::slice_from_raw_parts_mut(channel.leds, channel.count as usize);
+            drop(Box::from_raw(leds));
+            channel.leds = ptr::null_mut();
+        }
+        if !channel.gamma.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(channel.gamma, 256)));
+            channel.gamma = ptr::null_mut();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::WS2811Error;
+    use {ChannelBuilder, Controller, ControllerBuilder, StripType};
+
+    fn build(strip_type: StripType, brightness: u8) -> Controller {
+        ControllerBuilder::new()
+            .channel(0, ChannelBuilder::new().pin(18).count(2).strip_type(strip_type).brightness(brightness).build())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render_orders_and_scales_colors() {
+        let mut controller = build(StripType::Ws2811Grb, 255);
+        assert_eq!(controller.leds(0), &[[0; 4]; 2]);
+        // bytes are [b, g, r, w]
+        controller.leds_mut(0)[0] = [3, 2, 1, 0];
+        controller.leds_mut(0)[1] = [0, 0, 255, 0];
+        controller.render().unwrap();
+        assert_eq!(controller.device().wire(0), &[2, 1, 3, 0, 255, 0]);
+        assert_eq!(controller.device().renders(), 1);
+
+        let mut controller = build(StripType::Sk6812Rgbw, 127);
+        controller.leds_mut(0)[0] = [255, 0, 200, 100];
+        controller.render().unwrap();
+        assert_eq!(&controller.device().wire(0)[..4], &[100, 0, 127, 50]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut channel = ChannelBuilder::new();
+        channel.pin(4).count(1);
+        let result = ControllerBuilder::new().channel(0, channel.build()).build();
+        assert!(matches!(result, Err(WS2811Error::IllegalGpio)));
+
+        fail_next_init(ws2811_return_t::WS2811_ERROR_MMAP);
+        let result = ControllerBuilder::new().channel(0, channel.pin(18).build()).build();
+        assert!(matches!(result, Err(WS2811Error::Mmap)));
+
+        let mut controller = build(StripType::Ws2811Rgb, 255);
+        controller.device_mut().fail_render(ws2811_return_t::WS2811_ERROR_SPI_TRANSFER);
+        assert!(matches!(controller.render(), Err(WS2811Error::SpiTransfer)));
+        assert!(controller.render().is_ok());
+        assert_eq!(controller.device().renders(), 1);
+    }
+}
@@ -2,7 +2,7 @@ mod raw_color;
 pub use self::raw_color::{RawColor};
 
 mod strip_type;
-pub use self::strip_type::StripType;
+pub use self::strip_type::{ColorChannel, ParseStripTypeError, StripType};
 
 mod error;
 pub use self::error::{Result, WS2811Error};
@@ -1,4 +1,8 @@
+use std::{error, fmt};
 use std::os::raw::c_uint;
+use std::str::FromStr;
+
+use self::ColorChannel::{Blue as B, Green as G, Red as R, White as W};
 
 use super::super::bindings::{
     SK6812W_STRIP, SK6812_STRIP, SK6812_STRIP_BGRW, SK6812_STRIP_BRGW, SK6812_STRIP_GBRW,
@@ -6,7 +10,21 @@ use super::super::bindings::{
     WS2811_STRIP_GBR, WS2811_STRIP_GRB, WS2811_STRIP_RBG, WS2811_STRIP_RGB, WS2812_STRIP,
 };
 
-#[derive(Clone, Copy, Debug)]
+/// A single color channel of an LED.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorChannel {
+    Red,
+    Green,
+    Blue,
+    White,
+}
+
+/// The kind of LED strip, which decides the order that the color
+/// channels are sent in and whether there is a white channel.
+///
+/// Parses from and displays as names like `ws2811-grb` or `sk6812-rgbw`,
+/// ignoring case.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum StripType {
     Sk6812Rgbw,
     Sk6812Rbgw,
@@ -46,3 +64,126 @@ impl Into<c_uint> for StripType {
         }
     }
 }
+
+impl StripType {
+    pub const ALL: [StripType; 15] = [
+        StripType::Sk6812Rgbw,
+        StripType::Sk6812Rbgw,
+        StripType::Sk6812Gbrw,
+        StripType::Sk6812Grbw,
+        StripType::Sk6812Brgw,
+        StripType::Sk6812Bgrw,
+        StripType::Ws2811Rgb,
+        StripType::Ws2811Rbg,
+        StripType::Ws2811Grb,
+        StripType::Ws2811Gbr,
+        StripType::Ws2811Brg,
+        StripType::Ws2811Bgr,
+        StripType::Ws2812,
+        StripType::Sk6812,
+        StripType::Sk6812W,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StripType::Sk6812Rgbw => "sk6812-rgbw",
+            StripType::Sk6812Rbgw => "sk6812-rbgw",
+            StripType::Sk6812Gbrw => "sk6812-gbrw",
+            StripType::Sk6812Grbw => "sk6812-grbw",
+            StripType::Sk6812Brgw => "sk6812-brgw",
+            StripType::Sk6812Bgrw => "sk6812-bgrw",
+            StripType::Ws2811Rgb => "ws2811-rgb",
+            StripType::Ws2811Rbg => "ws2811-rbg",
+            StripType::Ws2811Grb => "ws2811-grb",
+            StripType::Ws2811Gbr => "ws2811-gbr",
+            StripType::Ws2811Brg => "ws2811-brg",
+            StripType::Ws2811Bgr => "ws2811-bgr",
+            StripType::Ws2812 => "ws2812",
+            StripType::Sk6812 => "sk6812",
+            StripType::Sk6812W => "sk6812w",
+        }
+    }
+
+    /// The order the channels go out on the wire. `Ws2812` and `Sk6812`
+    /// are GRB strips and `Sk6812W` is a GRBW one.
+    pub fn color_order(self) -> &'static [ColorChannel] {
+        match self {
+            StripType::Sk6812Rgbw => &[R, G, B, W],
+            StripType::Sk6812Rbgw => &[R, B, G, W],
+            StripType::Sk6812Gbrw => &[G, B, R, W],
+            StripType::Sk6812Grbw | StripType::Sk6812W => &[G, R, B, W],
+            StripType::Sk6812Brgw => &[B, R, G, W],
+            StripType::Sk6812Bgrw => &[B, G, R, W],
+            StripType::Ws2811Rgb => &[R, G, B],
+            StripType::Ws2811Rbg => &[R, B, G],
+            StripType::Ws2811Grb | StripType::Ws2812 | StripType::Sk6812 => &[G, R, B],
+            StripType::Ws2811Gbr => &[G, B, R],
+            StripType::Ws2811Brg => &[B, R, G],
+            StripType::Ws2811Bgr => &[B, G, R],
+        }
+    }
+
+    /// 3 for RGB strips and 4 for RGBW ones.
+    pub fn channel_count(self) -> usize {
+        self.color_order().len()
+    }
+
+    pub fn has_white(self) -> bool {
+        self.channel_count() == 4
+    }
+}
+
+impl fmt::Display for StripType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseStripTypeError(String);
+
+impl fmt::Display for ParseStripTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = StripType::ALL.iter().map(|strip| strip.name()).collect();
+        write!(f, "unknown strip type {:?}, expected one of: {}", self.0, names.join(", "))
+    }
+}
+
+impl error::Error for ParseStripTypeError {
+    fn description(&self) -> &str {
+        "unknown strip type"
+    }
+}
+
+impl FromStr for StripType {
+    type Err = ParseStripTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StripType::ALL.iter()
+            .find(|strip| strip.name().eq_ignore_ascii_case(s.trim()))
+            .cloned()
+            .ok_or_else(|| ParseStripTypeError(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_round_trip() {
+        for strip in StripType::ALL.iter() {
+            assert_eq!(strip.to_string().parse(), Ok(*strip));
+        }
+        assert_eq!("WS2811-GRB".parse(), Ok(StripType::Ws2811Grb));
+        assert!("ws2811-grbw".parse::<StripType>().is_err());
+    }
+
+    #[test]
+    fn test_channels() {
+        assert_eq!(StripType::Ws2812.color_order(), &[G, R, B]);
+        assert_eq!(StripType::Ws2811Bgr.channel_count(), 3);
+        assert!(!StripType::Ws2811Rgb.has_white());
+        assert!(StripType::Sk6812W.has_white());
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::Instant;
+
+use rpi_ws281x_display::Pixel;
+
+/// How much each new frame counts towards the frame time shown, which
+/// keeps the numbers readable.
+const SMOOTHING: f64 = 0.1;
+
+/// What the overlay on top of the LEDs knows about: what's playing, how
+/// fast frames are being rendered and where the mouse is.
+#[derive(Debug, Default)]
+pub struct Hud {
+    pub visible: bool,
+    animation: Option<String>,
+    elapsed: f64,
+    last_frame: Option<Instant>,
+    /// Seconds between frames, smoothed.
+    frame_time: Option<f64>,
+    /// The mouse position in window coordinates, while over the window.
+    pub cursor: Option<[f64; 2]>,
+}
+
+impl Hud {
+    pub fn set_now_playing(&mut self, animation: Option<&str>, elapsed: f64) {
+        self.animation = animation.map(|name| name.to_string());
+        self.elapsed = elapsed;
+    }
+
+    /// Counts a frame rendered at `now`.
+    pub fn frame(&mut self, now: Instant) {
+        if let Some(last) = self.last_frame {
+            let seconds = now.duration_since(last).as_secs_f64();
+            self.frame_time = Some(match self.frame_time {
+                Some(average) => average + (seconds - average) * SMOOTHING,
+                None => seconds,
+            });
+        }
+        self.last_frame = Some(now);
+    }
+
+    /// The LED under the mouse in a window of `width` by `height` showing
+    /// `cols` by `rows` LEDs.
+    pub fn hovered(&self, width: f64, height: f64, cols: usize, rows: usize) -> Option<(usize, usize)> {
+        let [x, y] = self.cursor?;
+        if x < 0.0 || y < 0.0 || x >= width || y >= height {
+            return None;
+        }
+        let col = ((x / width * cols as f64) as usize).min(cols.saturating_sub(1));
+        let row = ((y / height * rows as f64) as usize).min(rows.saturating_sub(1));
+        Some((col, row))
+    }
+
+    /// The text to show, one entry per line, with `hovered` being the LED
+    /// under the mouse and its color.
+    pub fn lines(&self, hovered: Option<(usize, usize, Pixel)>) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{} {:.1}s",
+            self.animation.as_ref().map_or("(nothing playing)", |name| name.as_str()),
+            self.elapsed
+        )];
+        if let Some(frame_time) = self.frame_time {
+            lines.push(format!("{:.1} fps {:.1} ms", 1.0 / frame_time.max(1e-6), frame_time * 1000.0));
+        }
+        if let Some((x, y, p)) = hovered {
+            lines.push(format!("x {} y {}: r {} g {} b {} w {}", x, y, p.r(), p.g(), p.b(), p.w()));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lines() {
+        let mut hud = Hud::default();
+        hud.set_now_playing(Some("fireflies"), 2.34);
+        let start = Instant::now();
+        hud.frame(start);
+        assert_eq!(hud.lines(None), vec!["fireflies 2.3s"]);
+        hud.frame(start + Duration::from_millis(50));
+        assert_eq!(hud.lines(Some((1, 2, Pixel::new(1u8, 2, 3, 4)))), vec![
+            "fireflies 2.3s",
+            "20.0 fps 50.0 ms",
+            "x 1 y 2: r 1 g 2 b 3 w 4",
+        ]);
+    }
+
+    #[test]
+    fn test_hovered() {
+        let mut hud = Hud::default();
+        assert_eq!(hud.hovered(700.0, 700.0, 7, 7), None);
+        hud.cursor = Some([150.0, 699.0]);
+        assert_eq!(hud.hovered(700.0, 700.0, 7, 7), Some((1, 6)));
+        hud.cursor = Some([700.0, 10.0]);
+        assert_eq!(hud.hovered(700.0, 700.0, 7, 7), None);
+    }
+}
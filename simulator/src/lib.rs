@@ -1,16 +1,28 @@
 extern crate glutin_window;
 extern crate graphics;
 extern crate opengl_graphics;
+extern crate lightbox;
 extern crate piston;
 extern crate rpi_ws281x_display;
 
-use piston::input::{Input, Event};
+use lightbox::{Command, Commands};
+use piston::input::{Button, ButtonArgs, ButtonState, Event, Input, Key, Motion};
 use glutin_window::{GlutinWindow, OpenGL};
 use graphics::types::Color as PistonColor;
 use graphics::Viewport;
-use opengl_graphics::GlGraphics;
+use opengl_graphics::{GlGraphics, GlyphCache, TextureSettings};
 use piston::window::{Window, WindowSettings};
-use rpi_ws281x_display::{Pixel, PixelDisplay};
+use rpi_ws281x_display::{Error, Pixel, PixelDisplay, Result};
+use std::time::Instant;
+
+mod hud;
+
+use hud::Hud;
+
+/// How much `+` and `-` change the brightness by.
+const BRIGHTNESS_STEP: i16 = 16;
+
+const HUD_FONT_SIZE: u32 = 14;
 
 struct MyPixel(Pixel);
 
@@ -31,22 +43,16 @@ impl From<MyPixel> for PistonColor {
     }
 }
 
-impl From<PistonColor> for MyPixel {
-    fn from(color: PistonColor) -> Self {
-        MyPixel(Pixel::new((color[0] * 255.0f32) as u32,
-                           (color[1] * 255.0f32) as u32,
-                           (color[2] * 255.0f32) as u32,
-                           (color[3] * 255.0f32) as u32,
-        ))
-    }
-}
-
 pub struct Simulator {
     graphics: GlGraphics,
     window: GlutinWindow,
     rows: usize,
     cols: usize,
-    buffer: Vec<PistonColor>,
+    buffer: Vec<Pixel>,
+    brightness: u8,
+    commands: Commands,
+    hud: Hud,
+    glyphs: GlyphCache<'static>,
     viewport_full: Viewport,
     pub width: u32,
     pub height: u32,
@@ -60,25 +66,31 @@ impl PixelDisplay for Simulator {
         self.cols
     }
 
-    fn set_at<T: Into<usize>>(&mut self, x: T, y: T, pixel: Pixel) {
-        let (x, y) = (x.into(), y.into());
-        self.buffer[x + y * self.cols] = MyPixel(pixel).into();
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
+        self.buffer[x + y * self.cols] = pixel;
+    }
+
+    fn get_at(&self, x: usize, y: usize) -> Pixel {
+        self.buffer[x + y * self.cols]
+    }
+
+    fn frame(&self) -> &[Pixel] {
+        &self.buffer
     }
 
-    fn get_at<T: Into<usize>>(&self, x: T, y: T) -> Pixel {
-        let (x, y) = (x.into(), y.into());
-        MyPixel::from(self.buffer[x + y * self.cols]).0
+    fn frame_mut(&mut self) -> &mut [Pixel] {
+        &mut self.buffer
     }
 
     fn get_brightness(&self) -> u8 {
-        return 255;
+        self.brightness
     }
 
     fn set_brightness(&mut self, brightness: u8) {
-
+        self.brightness = brightness;
     }
 
-    fn render(&mut self) {
+    fn render(&mut self) -> Result<()> {
         self.flush_input();
 
         use graphics::*;
@@ -90,26 +102,43 @@ impl PixelDisplay for Simulator {
         clear(BLACK, &mut self.graphics);
         for y in 0..self.rows() {
             for x in 0..self.cols() {
-                let mp: MyPixel = self.get_at(x, y).into();
+                let mp: MyPixel = self.buffer[x + y * self.cols].at_brightness(self.brightness).into();
                 let color: types::Color = mp.into();
                 ellipse(color, LED_RECT, ctx.transform.trans(x as f64 * self.width as f64 / self.cols() as f64, y as f64 * self.height as f64 / self.rows() as f64), &mut self.graphics);
             }
         }
+        self.hud.frame(Instant::now());
+        if self.hud.visible {
+            let (width, height) = (self.width as f64, self.height as f64);
+            let hovered = self.hud.hovered(width, height, self.cols, self.rows);
+            if let Some((x, y)) = hovered {
+                let rect = [x as f64 * LED_RECT[2], y as f64 * LED_RECT[3], LED_RECT[2], LED_RECT[3]];
+                Rectangle::new_border([1.0, 1.0, 1.0, 0.8], 1.0).draw(rect, &ctx.draw_state, ctx.transform, &mut self.graphics);
+            }
+            let lines = self.hud.lines(hovered.map(|(x, y)| (x, y, self.get_at(x, y))));
+            let line_height = HUD_FONT_SIZE as f64 * 1.4;
+            rectangle([0.0, 0.0, 0.0, 0.6], [0.0, 0.0, width, line_height * lines.len() as f64 + 8.0], ctx.transform, &mut self.graphics);
+            for (i, line) in lines.iter().enumerate() {
+                let transform = ctx.transform.trans(8.0, line_height * (i + 1) as f64);
+                Text::new_color([1.0, 1.0, 1.0, 1.0], HUD_FONT_SIZE)
+                    .draw(line, &mut self.glyphs, &ctx.draw_state, transform, &mut self.graphics)
+                    .map_err(|e| Error::backend(format!("couldn't draw the HUD: {:?}", e)))?;
+            }
+        }
         self.graphics.draw_end();
         self.window.swap_buffers();
+        Ok(())
     }
 
     fn clear(&mut self) {
-        for row in 0..self.rows() {
-            for col in 0..self.cols() {
-                self.set_at(col, row, Pixel::new(0u8, 0u8, 0u8, 0u8));
-            }
-        }
+        self.fill(Pixel::new(0u8, 0u8, 0u8, 0u8));
     }
 }
 
 impl Simulator {
-    pub fn new<T: Into<usize>, U: Into<u32>>(cols: T, rows: T, width: U, height: U) -> Self {
+    /// Opens the simulator window, which fails when there is no display
+    /// or OpenGL 3.2 isn't available.
+    pub fn new<T: Into<usize>, U: Into<u32>>(cols: T, rows: T, width: U, height: U) -> Result<Self> {
         let (cols, rows, width, height) = (cols.into(), rows.into(), width.into(), height.into());
         let opengl = OpenGL::V3_2;
         let window: GlutinWindow = WindowSettings::new(
@@ -119,12 +148,8 @@ impl Simulator {
             .exit_on_esc(true)
             .resizable(false)
             .build()
-            .expect("Error creating window for Lightbox Simulator");
-        let mut buffer = vec![];
-        buffer.reserve(cols * rows);
-        for _ in 0..(cols * rows) {
-            buffer.push([0.0, 0.0, 0.0, 1.0]);
-        }
+            .map_err(|e| Error::backend(format!("couldn't create the simulator window: {}", e)))?;
+        let buffer = vec![Pixel::new(0u8, 0u8, 0u8, 255u8); cols * rows];
         let window_size = window.size();
         let draw_size = window.draw_size();
         let viewport_full = Viewport {
@@ -132,23 +157,51 @@ impl Simulator {
             draw_size: draw_size.into(),
             window_size: window_size.into(),
         };
-        Self {
+        Ok(Self {
             graphics: GlGraphics::new(opengl),
             window,
             viewport_full,
             rows: rows.into(),
             cols: cols.into(),
             buffer,
+            brightness: 255,
+            commands: Commands::new(),
+            hud: Hud::default(),
+            glyphs: GlyphCache::from_bytes(rpi_ws281x_display::FONT, (), TextureSettings::new())
+                .map_err(|_| Error::backend("couldn't load the HUD font"))?,
             width,
             height,
-        }
+        })
+    }
+
+    /// The commands typed into the window, for `RunOptions::commands`:
+    ///
+    /// - left and right arrows for the previous and next animation
+    /// - space to pause and resume
+    /// - `.` to step a single frame
+    /// - `+` and `-` for brightness
+    /// - 1 to 9 to jump to that animation in the playlist, 0 for the 10th
+    ///
+    /// Closing the window quits, and `H` shows or hides the HUD.
+    pub fn commands(&self) -> Commands {
+        self.commands.clone()
+    }
+
+    /// Shows the animation that is playing and the measured frame rate
+    /// over the LEDs, and the color of the LED under the mouse.
+    pub fn set_hud_visible(&mut self, visible: bool) {
+        self.hud.visible = visible;
+    }
+
+    /// What the HUD says is playing, see `RunOptions::before_frame`.
+    pub fn set_now_playing(&mut self, animation: Option<&str>, elapsed: f64) {
+        self.hud.set_now_playing(animation, elapsed);
     }
 
     fn flush_input(&mut self) {
-        loop {
-            let thing = Window::poll_event(&mut self.window);
-            match Window::poll_event(&mut self.window) {
-                Some(Event::Input(Input::Resize(_), _)) => {
+        while let Some(event) = Window::poll_event(&mut self.window) {
+            match event {
+                Event::Input(Input::Resize(_), _) => {
                     let window_size = self.window.size();
                     let draw_size = self.window.draw_size();
                     self.viewport_full = Viewport {
@@ -157,9 +210,39 @@ impl Simulator {
                         window_size: window_size.into(),
                     }
                 }
-                None => break,
+                Event::Input(Input::Button(ButtonArgs { state: ButtonState::Press, button: Button::Keyboard(Key::H), .. }), _) => {
+                    self.hud.visible = !self.hud.visible;
+                }
+                Event::Input(Input::Button(ButtonArgs { state: ButtonState::Press, button: Button::Keyboard(key), .. }), _) => {
+                    if let Some(command) = command_for(key) {
+                        self.commands.push(command);
+                    }
+                }
+                Event::Input(Input::Move(Motion::MouseCursor(position)), _) => self.hud.cursor = Some(position),
+                Event::Input(Input::Cursor(false), _) => self.hud.cursor = None,
+                Event::Input(Input::Close(_), _) => self.commands.push(Command::Quit),
                 _ => {}
             }
         }
+        if self.window.should_close() {
+            self.commands.push(Command::Quit);
+        }
     }
 }
+
+fn command_for(key: Key) -> Option<Command> {
+    let command = match key {
+        Key::Left => Command::Previous,
+        Key::Right => Command::Next,
+        Key::Space => Command::TogglePause,
+        Key::Period => Command::Step,
+        Key::Plus | Key::Equals | Key::NumPadPlus => Command::AdjustBrightness(BRIGHTNESS_STEP),
+        Key::Minus | Key::NumPadMinus => Command::AdjustBrightness(-BRIGHTNESS_STEP),
+        Key::D0 => Command::Select(9),
+        key if key as u32 >= Key::D1 as u32 && key as u32 <= Key::D9 as u32 => {
+            Command::Select((key as u32 - Key::D1 as u32) as usize)
+        }
+        _ => return None,
+    };
+    Some(command)
+}
@@ -11,10 +11,17 @@ extern crate lightbox;
 use rpi_ws281x_display::animations::aaron::Fireflies;
 use rpi_ws281x_display::animations::snider::{BasicAnimation, CircleAnimation, StripeAnimation};
 use simulator::Simulator;
+use lightbox::RunOptions;
 use rpi_ws281x_display::animations::letters::Letters;
 
+use std::process;
+
 fn main() {
-    let simulator = Simulator::new(7usize, 7usize, 720u32, 720u32);
+    let simulator = Simulator::new(7usize, 7usize, 720u32, 720u32).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let commands = simulator.commands();
     let mut lightbox = lightbox::LightBox::new(
         simulator,
         vec![
@@ -26,6 +33,15 @@ fn main() {
         ],
         30,
     );
-    lightbox.run_forever();
+    let now_playing = |lightbox: &mut lightbox::LightBox<Simulator>| {
+        let elapsed = lightbox.elapsed();
+        let name = lightbox.current_name().map(|name| name.to_string());
+        lightbox.display.set_now_playing(name.as_deref(), elapsed);
+    };
+    let options = RunOptions { commands, before_frame: Box::new(now_playing) };
+    if let Err(e) = lightbox.run(options) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
 
@@ -0,0 +1,254 @@
+//! A `PixelDisplay` that draws the grid in a terminal, for watching
+//! animations over SSH or anywhere else without a window.
+extern crate rpi_ws281x_display;
+
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Stdout, Write};
+
+use rpi_ws281x_display::{Error, Pixel, PixelDisplay, Result};
+
+/// Every terminal cell shows two LEDs, the top one as the foreground
+/// color of this character and the bottom one as the background.
+const UPPER_HALF_BLOCK: char = '▀';
+
+/// The levels of each channel in the 6x6x6 color cube of 256 color
+/// terminals.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The escape sequences used for colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMode {
+    /// 24-bit color, which most modern terminals understand.
+    TrueColor,
+    /// The closest of the xterm 256 colors, for terminals without 24-bit
+    /// color such as the macOS Terminal or screen.
+    Ansi256,
+}
+
+impl ColorMode {
+    /// 24-bit color when the terminal says it supports it through
+    /// `COLORTERM`, like most of them do, or 256 colors otherwise.
+    pub fn detect() -> Self {
+        match env::var("COLORTERM") {
+            Ok(ref value) if value == "truecolor" || value == "24bit" => ColorMode::TrueColor,
+            _ => ColorMode::Ansi256,
+        }
+    }
+
+    /// The parameters of an SGR sequence setting the foreground, or the
+    /// background when `background` is set, to `(r, g, b)`.
+    fn sgr(self, (r, g, b): (u8, u8, u8), background: bool) -> String {
+        let target = if background { 48 } else { 38 };
+        match self {
+            ColorMode::TrueColor => format!("{};2;{};{};{}", target, r, g, b),
+            ColorMode::Ansi256 => format!("{};5;{}", target, ansi256(r, g, b)),
+        }
+    }
+}
+
+/// The closest color in the xterm palette, from the color cube or the
+/// grayscale ramp.
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        v => (v - 35) / 40,
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE_LEVELS[ri as usize], CUBE_LEVELS[gi as usize], CUBE_LEVELS[bi as usize]);
+
+    let average = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + 10 * gray_index;
+
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+    if distance((gray, gray, gray)) < distance(cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * ri + 6 * gi + bi
+    }
+}
+
+/// What an LED looks like on screen, with the white channel added to the
+/// others since there is no white to show it with.
+fn shade(pixel: Pixel, brightness: u8) -> (u8, u8, u8) {
+    let pixel = pixel.at_brightness(brightness);
+    let w = pixel.w();
+    (pixel.r().saturating_add(w), pixel.g().saturating_add(w), pixel.b().saturating_add(w))
+}
+
+/// Draws the grid with half block characters, two rows of LEDs to a line
+/// of text, redrawing it in place on every render.
+pub struct TerminalDisplay<W: Write = Stdout> {
+    out: W,
+    rows: usize,
+    cols: usize,
+    buffer: Vec<Pixel>,
+    brightness: u8,
+    mode: ColorMode,
+    /// The number of lines drawn by the last render, to go back up over.
+    drawn: usize,
+}
+
+impl TerminalDisplay {
+    /// A display on standard output, using the colors the terminal
+    /// supports.
+    pub fn new(cols: usize, rows: usize) -> Self {
+        TerminalDisplay::with_writer(io::stdout(), cols, rows, ColorMode::detect())
+    }
+}
+
+impl<W: Write> TerminalDisplay<W> {
+    pub fn with_writer(out: W, cols: usize, rows: usize, mode: ColorMode) -> Self {
+        TerminalDisplay {
+            out,
+            rows,
+            cols,
+            buffer: vec![Pixel::default(); rows * cols],
+            brightness: 255,
+            mode,
+            drawn: 0,
+        }
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.mode
+    }
+
+    pub fn set_color_mode(&mut self, mode: ColorMode) {
+        self.mode = mode;
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// The whole frame as text and escape sequences, starting with moving
+    /// the cursor back over the previous one.
+    fn draw(&self) -> String {
+        let mut text = String::new();
+        if self.drawn > 0 {
+            write!(text, "\x1b[{}A\r", self.drawn).unwrap();
+        } else {
+            // hide the cursor so that it doesn't flicker around the grid
+            text.push_str("\x1b[?25l");
+        }
+        for y in (0..self.rows).step_by(2) {
+            for x in 0..self.cols {
+                let top = shade(self.get_at(x, y), self.brightness);
+                let bottom = if y + 1 < self.rows {
+                    shade(self.get_at(x, y + 1), self.brightness)
+                } else {
+                    (0, 0, 0)
+                };
+                write!(text, "\x1b[{};{}m{}", self.mode.sgr(top, false), self.mode.sgr(bottom, true), UPPER_HALF_BLOCK).unwrap();
+            }
+            text.push_str("\x1b[0m\n");
+        }
+        text
+    }
+}
+
+impl<W: Write> PixelDisplay for TerminalDisplay<W> {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.cols
+    }
+
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
+        if x < self.cols && y < self.rows {
+            self.buffer[x + y * self.cols] = pixel;
+        }
+    }
+
+    fn get_at(&self, x: usize, y: usize) -> Pixel {
+        if x < self.cols && y < self.rows {
+            self.buffer[x + y * self.cols]
+        } else {
+            Pixel::default()
+        }
+    }
+
+    fn frame(&self) -> &[Pixel] {
+        &self.buffer
+    }
+
+    fn frame_mut(&mut self) -> &mut [Pixel] {
+        &mut self.buffer
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let text = self.draw();
+        self.out.write_all(text.as_bytes())
+            .and_then(|_| self.out.flush())
+            .map_err(Error::backend)?;
+        self.drawn = self.rows.div_ceil(2);
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.fill(Pixel::default());
+    }
+
+    fn get_brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+}
+
+impl<W: Write> Drop for TerminalDisplay<W> {
+    fn drop(&mut self) {
+        if self.drawn > 0 {
+            // give the cursor back, there's nothing to report a failure to
+            let _ = self.out.write_all(b"\x1b[?25h");
+            let _ = self.out.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut display = TerminalDisplay::with_writer(Vec::new(), 2, 3, ColorMode::TrueColor);
+        display.set_at(0, 0, Pixel::rgb(255, 0, 0));
+        display.set_at(1, 1, Pixel::new(0u8, 0, 100, 50));
+        display.set_at(0, 2, Pixel::rgb(0, 255, 0));
+        display.render().unwrap();
+        assert_eq!(String::from_utf8_lossy(display.get_ref()), concat!(
+            "\x1b[?25l",
+            "\x1b[38;2;255;0;0;48;2;0;0;0m▀\x1b[38;2;0;0;0;48;2;50;50;150m▀\x1b[0m\n",
+            "\x1b[38;2;0;255;0;48;2;0;0;0m▀\x1b[38;2;0;0;0;48;2;0;0;0m▀\x1b[0m\n",
+        ));
+
+        display.set_color_mode(ColorMode::Ansi256);
+        display.render().unwrap();
+        let output = String::from_utf8_lossy(display.get_ref()).into_owned();
+        assert!(output.ends_with(concat!(
+            "\x1b[2A\r",
+            "\x1b[38;5;196;48;5;16m▀\x1b[38;5;16;48;5;60m▀\x1b[0m\n",
+            "\x1b[38;5;46;48;5;16m▀\x1b[38;5;16;48;5;16m▀\x1b[0m\n",
+        )), "{:?}", output);
+    }
+
+    #[test]
+    fn test_ansi256() {
+        assert_eq!(ansi256(0, 0, 0), 16);
+        assert_eq!(ansi256(255, 255, 255), 231);
+        assert_eq!(ansi256(255, 135, 0), 208);
+        assert_eq!(ansi256(128, 128, 128), 244);
+        assert_eq!(ansi256(20, 20, 25), 233);
+    }
+}
@@ -0,0 +1,48 @@
+//! Plays a playlist in the terminal, e.g.
+//!
+//! ```text
+//! terminal --rows 8 --cols 16 fireflies:10 circle:5
+//! ```
+extern crate clap;
+extern crate lightbox;
+extern crate rpi_ws281x_display;
+extern crate terminal;
+
+use std::process;
+
+use clap::{App, Arg};
+use lightbox::{LightBox, RunOptions};
+use lightbox::cli::{self, Options};
+use lightbox::playlist::build_playlist;
+use terminal::{ColorMode, TerminalDisplay};
+
+fn run() -> Result<(), String> {
+    let playlist_help = cli::playlist_help();
+    let matches = App::new("terminal")
+        .about("Plays a lightbox playlist in the terminal")
+        .arg(cli::playlist_arg(&playlist_help))
+        .args(&cli::display_args())
+        .arg(Arg::with_name("color").long("color").takes_value(true).default_value("auto")
+            .possible_values(&["auto", "truecolor", "256"])
+            .help("Colors the terminal supports, auto looks at COLORTERM"))
+        .get_matches();
+
+    let options = Options::from_matches(&matches)?;
+
+    let mut display = TerminalDisplay::new(options.cols, options.rows);
+    match matches.value_of("color").unwrap() {
+        "truecolor" => display.set_color_mode(ColorMode::TrueColor),
+        "256" => display.set_color_mode(ColorMode::Ansi256),
+        _ => {}
+    }
+    let playlist = build_playlist(&options.playlist).map_err(|e| e.to_string())?;
+    let mut lightbox = LightBox::new(display, playlist, u64::from(options.fps));
+    lightbox.run(RunOptions::default()).map_err(|e| e.to_string())
+}
+
+fn main() {
+    if let Err(message) = run() {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,377 @@
+//! A simulator that shows the grid in a browser instead of an OpenGL
+//! window. It serves a page with a canvas and pushes every rendered frame
+//! to it over a WebSocket.
+extern crate rpi_ws281x_display;
+extern crate tungstenite;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rpi_ws281x_display::{Error, Pixel, PixelDisplay, Result};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+/// How many frames a slow browser may fall behind before frames are
+/// dropped for it.
+const BACKLOG: usize = 2;
+/// How long a browser gets to send its request, or to take a frame,
+/// before it's hung up on.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for a frame before looking for pings and closes from
+/// the browser again.
+const POLL: Duration = Duration::from_millis(100);
+/// How long to wait for the browser when looking for pings and closes
+/// between frames.
+const READ_POLL: Duration = Duration::from_millis(1);
+
+const INDEX_HTML: &str = include_str!("index.html");
+
+#[derive(Default)]
+struct Subscribers {
+    senders: Vec<SyncSender<Arc<Vec<u8>>>>,
+    latest: Option<Arc<Vec<u8>>>,
+}
+
+type Shared = Arc<Mutex<Subscribers>>;
+
+/// Serves the grid at `http://<address>/`. Every browser that opens it
+/// sees the same frames, starting with the last one rendered.
+pub struct WebSimulator {
+    rows: usize,
+    cols: usize,
+    buffer: Vec<Pixel>,
+    brightness: u8,
+    address: SocketAddr,
+    subscribers: Shared,
+    stopped: Arc<AtomicBool>,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl WebSimulator {
+    /// Starts serving on `address`, which fails when it can't be bound.
+    /// Port 0 picks a free port, see `address` for which.
+    pub fn bind<A: ToSocketAddrs>(address: A, cols: usize, rows: usize) -> Result<Self> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| Error::backend(format!("couldn't start the web simulator: {}", e)))?;
+        let address = listener.local_addr().map_err(Error::backend)?;
+        let subscribers = Shared::default();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let (shared, stop) = (subscribers.clone(), stopped.clone());
+        let listener = thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    // the browser went away, nothing to do about it
+                    let _ = serve(stream, &shared);
+                });
+            }
+        });
+
+        Ok(WebSimulator {
+            rows,
+            cols,
+            buffer: vec![Pixel::default(); rows * cols],
+            brightness: 255,
+            address,
+            subscribers,
+            stopped,
+            listener: Some(listener),
+        })
+    }
+
+    /// Where the page is served.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// A frame as sent to the page: the number of columns and rows as big
+    /// endian `u16`s, the brightness, then the RGBW bytes of every pixel
+    /// row by row.
+    fn encode(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(5 + self.buffer.len() * 4);
+        message.extend_from_slice(&(self.cols as u16).to_be_bytes());
+        message.extend_from_slice(&(self.rows as u16).to_be_bytes());
+        message.push(self.brightness);
+        for pixel in &self.buffer {
+            message.extend_from_slice(&[pixel.r(), pixel.g(), pixel.b(), pixel.w()]);
+        }
+        message
+    }
+}
+
+/// Answers one connection, either with the page or by streaming frames
+/// over a WebSocket until the browser goes away.
+fn serve(stream: TcpStream, subscribers: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("sec-websocket-key") {
+            key = parts.next().map(|value| value.trim().to_string());
+        }
+    }
+
+    let mut stream = stream;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    match (path, key) {
+        ("/ws", Some(key)) => {
+            write!(
+                stream,
+                "HTTP/1.1 101 Switching Protocols\r\n\
+                 Upgrade: websocket\r\n\
+                 Connection: Upgrade\r\n\
+                 Sec-WebSocket-Accept: {}\r\n\r\n",
+                derive_accept_key(key.as_bytes())
+            )?;
+            stream.flush()?;
+            stream.set_read_timeout(Some(READ_POLL))?;
+            let socket = WebSocket::from_partially_read(stream, reader.buffer().to_vec(), Role::Server, None);
+            stream_frames(socket, &subscribe(subscribers))
+        }
+        ("/", _) | ("/index.html", _) => respond(&mut stream, "200 OK", "text/html; charset=utf-8", INDEX_HTML),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+    }
+}
+
+/// Sends every frame to the browser as a binary message, answering its
+/// pings in between, until either side closes the connection.
+fn stream_frames(mut socket: WebSocket<TcpStream>, frames: &Receiver<Arc<Vec<u8>>>) -> io::Result<()> {
+    loop {
+        match frames.recv_timeout(POLL) {
+            Ok(frame) => socket.send(Message::Binary(frame.to_vec())).map_err(io_error)?,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                socket.close(None).map_err(io_error)?;
+                return socket.flush().map_err(io_error);
+            }
+        }
+        // pongs and the answer to a close go out with the next read
+        match socket.read() {
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(ref e))
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(io_error(e)),
+        }
+    }
+}
+
+/// The I/O error behind a WebSocket error, if there is one.
+fn io_error(error: tungstenite::Error) -> io::Error {
+    match error {
+        tungstenite::Error::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    )?;
+    stream.flush()
+}
+
+/// Starts listening for frames, beginning with the last one rendered.
+fn subscribe(subscribers: &Shared) -> Receiver<Arc<Vec<u8>>> {
+    let (tx, rx) = sync_channel(BACKLOG);
+    let mut subscribers = subscribers.lock().unwrap();
+    if let Some(ref latest) = subscribers.latest {
+        let _ = tx.try_send(latest.clone());
+    }
+    subscribers.senders.push(tx);
+    rx
+}
+
+impl PixelDisplay for WebSimulator {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.cols
+    }
+
+    fn set_at(&mut self, x: usize, y: usize, pixel: Pixel) {
+        if x < self.cols && y < self.rows {
+            self.buffer[x + y * self.cols] = pixel;
+        }
+    }
+
+    fn get_at(&self, x: usize, y: usize) -> Pixel {
+        if x < self.cols && y < self.rows {
+            self.buffer[x + y * self.cols]
+        } else {
+            Pixel::default()
+        }
+    }
+
+    fn frame(&self) -> &[Pixel] {
+        &self.buffer
+    }
+
+    fn frame_mut(&mut self) -> &mut [Pixel] {
+        &mut self.buffer
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let frame = Arc::new(self.encode());
+        let mut subscribers = self.subscribers.lock().unwrap();
+        // a full queue means the browser is slow, so it misses this frame;
+        // a disconnected one means it has gone away
+        subscribers.senders.retain(|tx| match tx.try_send(frame.clone()) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+        subscribers.latest = Some(frame);
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.fill(Pixel::default());
+    }
+
+    fn get_brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+}
+
+impl Drop for WebSimulator {
+    fn drop(&mut self) {
+        // ends the streams, which closes the connections
+        self.subscribers.lock().unwrap().senders.clear();
+
+        // the listener only notices it should stop once someone connects
+        self.stopped.store(true, Ordering::SeqCst);
+        let mut address = self.address;
+        if address.ip().is_unspecified() {
+            address.set_ip(match address {
+                SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        if TcpStream::connect(address).is_ok() {
+            if let Some(listener) = self.listener.take() {
+                let _ = listener.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn request(address: SocketAddr, request: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        stream
+    }
+
+    /// Opens a WebSocket, returning it once the handshake is done.
+    fn open(address: SocketAddr) -> BufReader<TcpStream> {
+        let stream = request(address, concat!(
+            "GET /ws HTTP/1.1\r\n",
+            "Upgrade: websocket\r\n",
+            "Connection: Upgrade\r\n",
+            "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n",
+            "Sec-WebSocket-Version: 13\r\n\r\n",
+        ));
+        let mut reader = BufReader::new(stream);
+        let mut headers = String::new();
+        while !headers.ends_with("\r\n\r\n") {
+            reader.read_line(&mut headers).unwrap();
+        }
+        assert!(headers.starts_with("HTTP/1.1 101"));
+        assert!(headers.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        reader
+    }
+
+    #[test]
+    fn test_serves_page() {
+        let display = WebSimulator::bind("127.0.0.1:0", 2, 2).unwrap();
+        let mut response = String::new();
+        request(display.address(), "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .read_to_string(&mut response)
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("<canvas"));
+
+        response.clear();
+        request(display.address(), "GET /nope HTTP/1.1\r\n\r\n").read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_streams_frames() {
+        let mut display = WebSimulator::bind("127.0.0.1:0", 2, 1).unwrap();
+        display.set_at(1, 0, Pixel::new(1u8, 2, 3, 4));
+        display.set_brightness(100);
+        display.render().unwrap();
+
+        let mut reader = open(display.address());
+        let mut message = [0u8; 15];
+        reader.read_exact(&mut message).unwrap();
+        assert_eq!(message, [0x82, 13, 0, 2, 0, 1, 100, 0, 0, 0, 0, 1, 2, 3, 4]);
+
+        // frames rendered afterwards follow, and dropping the display
+        // closes the stream
+        display.clear();
+        display.render().unwrap();
+        drop(display);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, [0x82, 13, 0, 2, 0, 1, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0x88, 0]);
+    }
+
+    #[test]
+    fn test_answers_pings_and_closes() {
+        let display = WebSimulator::bind("127.0.0.1:0", 2, 1).unwrap();
+        let mut reader = open(display.address());
+
+        // frames from the browser are masked, an all zero mask keeps the
+        // payload as it is
+        reader.get_mut().write_all(&[0x89, 0x82, 0, 0, 0, 0, b'h', b'i']).unwrap();
+        let mut pong = [0u8; 4];
+        reader.read_exact(&mut pong).unwrap();
+        assert_eq!(pong, [0x8a, 2, b'h', b'i']);
+
+        reader.get_mut().write_all(&[0x88, 0x80, 0, 0, 0, 0]).unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, [0x88, 0]);
+    }
+
+    #[test]
+    fn test_stops_listening_when_dropped() {
+        let display = WebSimulator::bind("127.0.0.1:0", 1, 1).unwrap();
+        let address = display.address();
+        drop(display);
+        assert!(TcpStream::connect(address).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+//! Plays a playlist in the browser, e.g.
+//!
+//! ```text
+//! web_simulator --port 8080 fireflies:10 circle:5
+//! ```
+extern crate clap;
+extern crate lightbox;
+extern crate rpi_ws281x_display;
+extern crate web_simulator;
+
+use std::process;
+
+use clap::{App, Arg};
+use lightbox::{LightBox, RunOptions};
+use lightbox::cli::{self, Options};
+use lightbox::playlist::build_playlist;
+use web_simulator::WebSimulator;
+
+fn run() -> Result<(), String> {
+    let playlist_help = cli::playlist_help();
+    let matches = App::new("web_simulator")
+        .about("Plays a lightbox playlist in the browser")
+        .arg(cli::playlist_arg(&playlist_help))
+        .args(&cli::display_args())
+        .arg(Arg::with_name("address").long("address").takes_value(true).default_value("127.0.0.1")
+            .help("Address to serve on, 0.0.0.0 to watch from other machines"))
+        .arg(Arg::with_name("port").short("p").long("port").takes_value(true).default_value("8080"))
+        .get_matches();
+
+    let options = Options::from_matches(&matches)?;
+
+    let address = (matches.value_of("address").unwrap(), cli::parse::<u16>(&matches, "port")?);
+    let display = WebSimulator::bind(address, options.cols, options.rows)
+        .map_err(|e| e.to_string())?;
+    println!("Serving the simulator on http://{}/", display.address());
+    let playlist = build_playlist(&options.playlist).map_err(|e| e.to_string())?;
+    let mut lightbox = LightBox::new(display, playlist, u64::from(options.fps));
+    lightbox.run(RunOptions::default()).map_err(|e| e.to_string())
+}
+
+fn main() {
+    if let Err(message) = run() {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}